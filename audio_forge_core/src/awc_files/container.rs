@@ -0,0 +1,704 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+
+use crate::utils::adpcm;
+
+// "ADAT" in little endian
+const MAGIC: u32 = 0x54414441;
+const VERSION: u16 = 1;
+
+const FLAG_CHUNK_INDICES: u16 = 1 << 0;
+const FLAG_MULTI_CHANNEL: u16 = 1 << 2;
+// The high byte of the flags is always set in files written by R*
+const FLAGS_BASE: u16 = 0xFF00;
+
+const STREAM_ID_MASK: u32 = 0x1FFFFFFF;
+const CHUNK_FIELD_MASK: u64 = 0x0FFFFFFF;
+const DATA_ALIGNMENT: usize = 16;
+
+/// Chunk type ids, these are the lowest byte of the joaat hash of the chunk name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChunkType {
+    Data = 0x55,
+    Format = 0xFA,
+    Peak = 0x36,
+    StreamFormat = 0x48,
+    SeekTable = 0xA3,
+    Markers = 0xBD,
+}
+
+impl TryFrom<u8> for ChunkType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0x55 => Ok(ChunkType::Data),
+            0xFA => Ok(ChunkType::Format),
+            0x36 => Ok(ChunkType::Peak),
+            0x48 => Ok(ChunkType::StreamFormat),
+            0xA3 => Ok(ChunkType::SeekTable),
+            0xBD => Ok(ChunkType::Markers),
+            _ => Err(anyhow!("unknown chunk type {:#04x}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Codec {
+    Pcm = 0,
+    Adpcm = 4,
+}
+
+impl TryFrom<u8> for Codec {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Codec::Pcm),
+            4 => Ok(Codec::Adpcm),
+            _ => Err(anyhow!("unsupported codec {}", value)),
+        }
+    }
+}
+
+impl Codec {
+    /// Decodes `sample_count` mono samples from the data of a stream
+    pub fn decode(&self, data: &[u8], sample_count: usize) -> Vec<i16> {
+        match self {
+            Codec::Pcm => data
+                .chunks_exact(2)
+                .take(sample_count)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect(),
+            Codec::Adpcm => adpcm::decode(data, sample_count),
+        }
+    }
+}
+
+/// Little endian reader over the bytes of a chunk
+struct ChunkReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.remaining() < N {
+            return Err(anyhow!("unexpected end of awc data"));
+        }
+        let mut buf = [0; N];
+        buf.copy_from_slice(&self.bytes[self.pos..self.pos + N]);
+        self.pos += N;
+        Ok(buf)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatChunk {
+    pub samples: u32,
+    pub loop_point: i32,
+    pub sample_rate: u16,
+    pub headroom: i16,
+    pub loop_begin: u16,
+    pub loop_end: u16,
+    pub play_end: u16,
+    pub play_begin: u8,
+    pub codec: Codec,
+    pub peak: u32,
+}
+
+impl FormatChunk {
+    const SIZE: usize = 24;
+
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.samples.to_le_bytes());
+        buf.extend_from_slice(&self.loop_point.to_le_bytes());
+        buf.extend_from_slice(&self.sample_rate.to_le_bytes());
+        buf.extend_from_slice(&self.headroom.to_le_bytes());
+        buf.extend_from_slice(&self.loop_begin.to_le_bytes());
+        buf.extend_from_slice(&self.loop_end.to_le_bytes());
+        buf.extend_from_slice(&self.play_end.to_le_bytes());
+        buf.push(self.play_begin);
+        buf.push(self.codec as u8);
+        buf.extend_from_slice(&self.peak.to_le_bytes());
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut r = ChunkReader::new(bytes);
+        let mut format = Self {
+            samples: r.u32()?,
+            loop_point: r.i32()?,
+            sample_rate: r.u16()?,
+            headroom: r.i16()?,
+            loop_begin: r.u16()?,
+            loop_end: r.u16()?,
+            play_end: r.u16()?,
+            play_begin: r.u8()?,
+            codec: r.u8()?.try_into()?,
+            peak: 0,
+        };
+        // Older containers don't have the peak value
+        if r.remaining() >= 4 {
+            format.peak = r.u32()?;
+        }
+        Ok(format)
+    }
+}
+
+/// Per channel info of a multi channel (streamed) container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamFormatChannel {
+    pub id: u32,
+    pub samples: u32,
+    pub headroom: i16,
+    pub sample_rate: u16,
+    pub codec: Codec,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamFormatChunk {
+    pub block_count: u32,
+    pub block_size: u32,
+    pub channels: Vec<StreamFormatChannel>,
+}
+
+impl StreamFormatChunk {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.block_count.to_le_bytes());
+        buf.extend_from_slice(&self.block_size.to_le_bytes());
+        buf.extend_from_slice(&(self.channels.len() as u32).to_le_bytes());
+        for channel in &self.channels {
+            buf.extend_from_slice(&channel.id.to_le_bytes());
+            buf.extend_from_slice(&channel.samples.to_le_bytes());
+            buf.extend_from_slice(&channel.headroom.to_le_bytes());
+            buf.extend_from_slice(&channel.sample_rate.to_le_bytes());
+            buf.push(channel.codec as u8);
+            buf.push(0);
+            buf.extend_from_slice(&0u16.to_le_bytes());
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut r = ChunkReader::new(bytes);
+        let block_count = r.u32()?;
+        let block_size = r.u32()?;
+        let channel_count = r.u32()?;
+        let mut channels = vec![];
+        for _ in 0..channel_count {
+            let channel = StreamFormatChannel {
+                id: r.u32()?,
+                samples: r.u32()?,
+                headroom: r.i16()?,
+                sample_rate: r.u16()?,
+                codec: r.u8()?.try_into()?,
+            };
+            r.take::<3>()?;
+            channels.push(channel);
+        }
+        Ok(Self {
+            block_count,
+            block_size,
+            channels,
+        })
+    }
+}
+
+/// Samples covered by every value of the peak chunk
+pub const PEAK_BLOCK_SIZE: usize = 4096;
+
+/// Largest absolute sample value of every block of a stream and of the whole stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Peaks {
+    pub blocks: Vec<u16>,
+    pub overall: u32,
+}
+
+impl Peaks {
+    pub fn from_samples(samples: &[i16]) -> Self {
+        let blocks: Vec<u16> = samples
+            .chunks(PEAK_BLOCK_SIZE)
+            .map(|block| block.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0))
+            .collect();
+        let overall = blocks.iter().copied().max().unwrap_or(0).into();
+        Self { blocks, overall }
+    }
+}
+
+/// Event at a sample position in the stream, e.g. beats or lip sync triggers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub name: u32,
+    pub value: u32,
+    pub sample_offset: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    Peak(Vec<u16>),
+    Data(Vec<u8>),
+    Format(FormatChunk),
+    StreamFormat(StreamFormatChunk),
+    // Start sample of every block in the data chunk
+    SeekTable(Vec<u32>),
+    Markers(Vec<Marker>),
+}
+
+impl Chunk {
+    pub fn chunk_type(&self) -> ChunkType {
+        match self {
+            Chunk::Peak(_) => ChunkType::Peak,
+            Chunk::Data(_) => ChunkType::Data,
+            Chunk::Format(_) => ChunkType::Format,
+            Chunk::StreamFormat(_) => ChunkType::StreamFormat,
+            Chunk::SeekTable(_) => ChunkType::SeekTable,
+            Chunk::Markers(_) => ChunkType::Markers,
+        }
+    }
+
+    fn parse(chunk_type: ChunkType, bytes: &[u8]) -> Result<Self> {
+        let chunk = match chunk_type {
+            ChunkType::Peak => Chunk::Peak(
+                bytes
+                    .chunks_exact(2)
+                    .map(|p| u16::from_le_bytes([p[0], p[1]]))
+                    .collect(),
+            ),
+            ChunkType::Data => Chunk::Data(bytes.to_vec()),
+            ChunkType::Format => Chunk::Format(FormatChunk::parse(bytes)?),
+            ChunkType::StreamFormat => Chunk::StreamFormat(StreamFormatChunk::parse(bytes)?),
+            ChunkType::SeekTable => Chunk::SeekTable(
+                bytes
+                    .chunks_exact(4)
+                    .map(|e| u32::from_le_bytes([e[0], e[1], e[2], e[3]]))
+                    .collect(),
+            ),
+            ChunkType::Markers => {
+                let mut r = ChunkReader::new(bytes);
+                let mut markers = vec![];
+                while r.remaining() >= 16 {
+                    markers.push(Marker {
+                        name: r.u32()?,
+                        value: r.u32()?,
+                        sample_offset: r.u32()?,
+                    });
+                    r.u32()?;
+                }
+                Chunk::Markers(markers)
+            }
+        };
+        Ok(chunk)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        match self {
+            Chunk::Peak(peaks) => {
+                for peak in peaks {
+                    buf.extend_from_slice(&peak.to_le_bytes());
+                }
+            }
+            Chunk::Data(data) => buf.extend_from_slice(data),
+            Chunk::Format(format) => {
+                buf.reserve(FormatChunk::SIZE);
+                format.write_to(&mut buf);
+            }
+            Chunk::StreamFormat(format) => format.write_to(&mut buf),
+            Chunk::SeekTable(entries) => {
+                for entry in entries {
+                    buf.extend_from_slice(&entry.to_le_bytes());
+                }
+            }
+            Chunk::Markers(markers) => {
+                for marker in markers {
+                    buf.extend_from_slice(&marker.name.to_le_bytes());
+                    buf.extend_from_slice(&marker.value.to_le_bytes());
+                    buf.extend_from_slice(&marker.sample_offset.to_le_bytes());
+                    buf.extend_from_slice(&0u32.to_le_bytes());
+                }
+            }
+        }
+        buf
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stream {
+    /// Lower 29 bits of the joaat hash of the stream name
+    pub id: u32,
+    pub chunks: Vec<Chunk>,
+}
+
+pub fn stream_id(name: &str) -> u32 {
+    crate::utils::hash::name_hash(name) & STREAM_ID_MASK
+}
+
+impl Stream {
+    pub fn new(name: &str, chunks: Vec<Chunk>) -> Self {
+        Self {
+            id: stream_id(name),
+            chunks,
+        }
+    }
+
+    /// The stream holding the interleaved data of all channels in a multi channel container
+    pub fn multi_channel_header(chunks: Vec<Chunk>) -> Self {
+        Self { id: 0, chunks }
+    }
+
+    pub fn format(&self) -> Option<&FormatChunk> {
+        self.chunks.iter().find_map(|c| match c {
+            Chunk::Format(format) => Some(format),
+            _ => None,
+        })
+    }
+
+    pub fn stream_format(&self) -> Option<&StreamFormatChunk> {
+        self.chunks.iter().find_map(|c| match c {
+            Chunk::StreamFormat(format) => Some(format),
+            _ => None,
+        })
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        self.chunks.iter().find_map(|c| match c {
+            Chunk::Data(data) => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        self.chunks
+            .iter()
+            .find_map(|c| match c {
+                Chunk::Markers(markers) => Some(markers.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Decoded samples of a single channel stream
+    pub fn decode_samples(&self) -> Result<Vec<i16>> {
+        let format = self
+            .format()
+            .ok_or(anyhow!("stream {:08X} has no format chunk", self.id))?;
+        let data = self
+            .data()
+            .ok_or(anyhow!("stream {:08X} has no data chunk", self.id))?;
+        Ok(format.codec.decode(data, format.samples as usize))
+    }
+}
+
+/// Binary AudioWaveContainer (.awc)
+///
+/// Layout:
+/// - header: magic, version, flags, stream count and data offset
+/// - chunk index table (optional): index of the first chunk info of every stream
+/// - stream info table: `chunk_count << 29 | stream id` per stream
+/// - chunk info table: `type << 56 | size << 28 | offset` per chunk
+/// - chunk bodies
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Container {
+    pub chunk_indices: bool,
+    pub multi_channel: bool,
+    pub streams: Vec<Stream>,
+}
+
+impl Container {
+    pub fn new(streams: Vec<Stream>) -> Self {
+        Self {
+            chunk_indices: true,
+            multi_channel: false,
+            streams,
+        }
+    }
+
+    fn flags(&self) -> u16 {
+        let mut flags = FLAGS_BASE;
+        if self.chunk_indices {
+            flags |= FLAG_CHUNK_INDICES;
+        }
+        if self.multi_channel {
+            flags |= FLAG_MULTI_CHANNEL;
+        }
+        flags
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        // The game does a binary search on the stream ids
+        let mut streams: Vec<&Stream> = self.streams.iter().collect();
+        streams.sort_by_key(|s| s.id);
+
+        let chunk_count: usize = streams.iter().map(|s| s.chunks.len()).sum();
+        let mut data_offset = 16 + streams.len() * 4 + chunk_count * 8;
+        if self.chunk_indices {
+            data_offset += streams.len() * 2;
+        }
+
+        let mut header = Vec::with_capacity(data_offset);
+        header.extend_from_slice(&MAGIC.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&self.flags().to_le_bytes());
+        header.extend_from_slice(&(streams.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(data_offset as u32).to_le_bytes());
+
+        if self.chunk_indices {
+            let mut index = 0;
+            for stream in &streams {
+                header.extend_from_slice(&(index as u16).to_le_bytes());
+                index += stream.chunks.len();
+            }
+        }
+
+        for stream in &streams {
+            if stream.chunks.len() > 7 {
+                return Err(anyhow!("stream {:08X} has more than 7 chunks", stream.id));
+            }
+            let info = (stream.chunks.len() as u32) << 29 | (stream.id & STREAM_ID_MASK);
+            header.extend_from_slice(&info.to_le_bytes());
+        }
+
+        let mut body: Vec<u8> = vec![];
+        for stream in &streams {
+            for chunk in &stream.chunks {
+                if chunk.chunk_type() == ChunkType::Data {
+                    let padding = (DATA_ALIGNMENT - (data_offset + body.len()) % DATA_ALIGNMENT)
+                        % DATA_ALIGNMENT;
+                    body.resize(body.len() + padding, 0);
+                }
+
+                let offset = data_offset + body.len();
+                let bytes = chunk.to_bytes();
+                if offset as u64 > CHUNK_FIELD_MASK || bytes.len() as u64 > CHUNK_FIELD_MASK {
+                    return Err(anyhow!("awc container exceeds the maximum size of 256MB"));
+                }
+
+                let info =
+                    (chunk.chunk_type() as u64) << 56 | (bytes.len() as u64) << 28 | offset as u64;
+                header.extend_from_slice(&info.to_le_bytes());
+                body.extend_from_slice(&bytes);
+            }
+        }
+
+        header.extend_from_slice(&body);
+        Ok(header)
+    }
+
+    pub fn read(path: &Path) -> Result<Container> {
+        let bytes = fs::read(path)?;
+        Container::parse(&bytes).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Container> {
+        let mut r = ChunkReader::new(bytes);
+        if r.u32()? != MAGIC {
+            return Err(anyhow!("not an awc file"));
+        }
+        let version = r.u16()?;
+        if version != VERSION {
+            return Err(anyhow!("unsupported awc version {}", version));
+        }
+        let flags = r.u16()?;
+        let stream_count = r.u32()? as usize;
+        let _data_offset = r.u32()?;
+
+        let chunk_indices = flags & FLAG_CHUNK_INDICES != 0;
+        if chunk_indices {
+            for _ in 0..stream_count {
+                r.u16()?;
+            }
+        }
+
+        let mut stream_infos = vec![];
+        for _ in 0..stream_count {
+            let info = r.u32()?;
+            stream_infos.push((info & STREAM_ID_MASK, (info >> 29) as usize));
+        }
+
+        let mut streams = vec![];
+        for (id, chunk_count) in stream_infos {
+            let mut chunk_infos = vec![];
+            for _ in 0..chunk_count {
+                chunk_infos.push(r.u64()?);
+            }
+
+            let mut chunks = vec![];
+            for info in chunk_infos {
+                let raw_type = (info >> 56) as u8;
+                let size = ((info >> 28) & CHUNK_FIELD_MASK) as usize;
+                let offset = (info & CHUNK_FIELD_MASK) as usize;
+                let body = bytes
+                    .get(offset..offset + size)
+                    .ok_or(anyhow!("chunk of stream {:08X} is out of bounds", id))?;
+
+                match ChunkType::try_from(raw_type) {
+                    Ok(chunk_type) => chunks.push(Chunk::parse(chunk_type, body)?),
+                    Err(err) => warn!("Skipping chunk of stream {:08X}: {}", id, err),
+                }
+            }
+            streams.push(Stream { id, chunks });
+        }
+
+        Ok(Container {
+            chunk_indices,
+            multi_channel: flags & FLAG_MULTI_CHANNEL != 0,
+            streams,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let bytes = self.to_bytes()?;
+        let mut f = File::create(path)?;
+        f.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hash::joaat;
+
+    fn format(samples: u32, codec: Codec) -> FormatChunk {
+        FormatChunk {
+            samples,
+            loop_point: -1,
+            sample_rate: 32000,
+            headroom: -161,
+            loop_begin: 0,
+            loop_end: 0,
+            play_end: 0,
+            play_begin: 0,
+            codec,
+            peak: 1234,
+        }
+    }
+
+    fn simple_container() -> Container {
+        let samples: Vec<i16> = (0..1000).map(|i| (i * 37 % 2000 - 1000) as i16).collect();
+        let pcm: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        Container::new(vec![
+            Stream::new(
+                "boom",
+                vec![
+                    Chunk::Peak(vec![1000]),
+                    Chunk::Data(pcm),
+                    Chunk::Format(format(1000, Codec::Pcm)),
+                    Chunk::Markers(vec![Marker {
+                        name: joaat("beat"),
+                        value: 1,
+                        sample_offset: 500,
+                    }]),
+                ],
+            ),
+            Stream::new(
+                "boom_adpcm",
+                vec![
+                    Chunk::Peak(vec![]),
+                    Chunk::Data(adpcm::encode(&samples)),
+                    Chunk::Format(format(1000, Codec::Adpcm)),
+                ],
+            ),
+        ])
+    }
+
+    // Streams are written sorted by id
+    fn sorted(mut container: Container) -> Container {
+        container.streams.sort_by_key(|s| s.id);
+        container
+    }
+
+    #[test]
+    fn simple_container_round_trip() {
+        let container = simple_container();
+        let path =
+            std::env::temp_dir().join(format!("audio_forge_test_{}.awc", std::process::id()));
+        container.write(&path).unwrap();
+        let read = Container::read(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(read.unwrap(), sorted(container));
+    }
+
+    #[test]
+    fn multi_channel_container_round_trip() {
+        let container = Container {
+            chunk_indices: true,
+            multi_channel: true,
+            streams: vec![
+                Stream::multi_channel_header(vec![
+                    Chunk::StreamFormat(StreamFormatChunk {
+                        block_count: 1,
+                        block_size: 0x800,
+                        channels: vec![StreamFormatChannel {
+                            id: stream_id("track_left"),
+                            samples: 4,
+                            headroom: -161,
+                            sample_rate: 48000,
+                            codec: Codec::Pcm,
+                        }],
+                    }),
+                    Chunk::Data(vec![7; 0x800]),
+                    Chunk::SeekTable(vec![0]),
+                ]),
+                Stream::new("track_left", vec![Chunk::Peak(vec![3, 4])]),
+            ],
+        };
+        let bytes = container.to_bytes().unwrap();
+        assert_eq!(Container::parse(&bytes).unwrap(), sorted(container));
+    }
+
+    #[test]
+    fn data_chunks_are_aligned() {
+        let bytes = simple_container().to_bytes().unwrap();
+        let container = Container::parse(&bytes).unwrap();
+        let stream_count = container.streams.len();
+        let chunk_count: usize = container.streams.iter().map(|s| s.chunks.len()).sum();
+        let table = 16 + stream_count * 2 + stream_count * 4;
+        for i in 0..chunk_count {
+            let offset = table + i * 8;
+            let info = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+            if (info >> 56) as u8 == ChunkType::Data as u8 {
+                assert_eq!((info & CHUNK_FIELD_MASK) as usize % DATA_ALIGNMENT, 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use anyhow::{anyhow, Result};
+
+use super::container::{
+    self, Chunk, Codec, Container, Peaks, Stream, StreamFormatChannel, StreamFormatChunk,
+};
+use crate::utils::adpcm;
+
+// Block size used by the game for streamed (radio) containers
+pub const BLOCK_SIZE: usize = 0x80000;
+// Channel data is split in frames of this size, this matches the ADPCM block size
+const FRAME_SIZE: usize = adpcm::BLOCK_SIZE;
+const BLOCK_HEADER_ALIGNMENT: usize = 0x800;
+// start_entry, entry_count, samples to skip and sample count
+const BLOCK_CHANNEL_INFO_SIZE: usize = 16;
+
+pub struct StreamChannel {
+    pub name: String,
+    pub sample_rate: u16,
+    pub headroom: i16,
+    pub codec: Codec,
+    pub samples: Vec<i16>,
+}
+
+impl StreamChannel {
+    fn samples_per_frame(&self) -> usize {
+        match self.codec {
+            Codec::Pcm => FRAME_SIZE / 2,
+            Codec::Adpcm => adpcm::SAMPLES_PER_BLOCK,
+        }
+    }
+
+    // Encoded channel data split in frames, the last frame is padded to the frame size
+    fn encode_frames(&self) -> Vec<Vec<u8>> {
+        let data = match self.codec {
+            Codec::Pcm => self.samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            Codec::Adpcm => adpcm::encode(&self.samples),
+        };
+        data.chunks(FRAME_SIZE)
+            .map(|frame| {
+                let mut frame = frame.to_vec();
+                frame.resize(FRAME_SIZE, 0);
+                frame
+            })
+            .collect()
+    }
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn block_header_size(channel_count: usize, frames_per_block: usize) -> usize {
+    align(
+        channel_count * (BLOCK_CHANNEL_INFO_SIZE + frames_per_block * 4),
+        BLOCK_HEADER_ALIGNMENT,
+    )
+}
+
+fn frames_per_block(channel_count: usize) -> usize {
+    let mut frames = BLOCK_SIZE / (channel_count * FRAME_SIZE);
+    while frames > 0
+        && block_header_size(channel_count, frames) + channel_count * frames * FRAME_SIZE
+            > BLOCK_SIZE
+    {
+        frames -= 1;
+    }
+    frames
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    let bytes = bytes
+        .get(offset..offset + 4)
+        .ok_or(anyhow!("unexpected end of stream block"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes all channels of a multi channel container built with [`build`].
+/// Channel names are unknown, so they are named after their stream id (`hash_XXXXXXXX`).
+pub fn read_channels(container: &Container) -> Result<Vec<StreamChannel>> {
+    let header = container
+        .streams
+        .iter()
+        .find(|s| s.id == 0)
+        .ok_or(anyhow!("multi channel container has no header stream"))?;
+    let format = header
+        .stream_format()
+        .ok_or(anyhow!("multi channel container has no stream format"))?;
+    let data = header
+        .data()
+        .ok_or(anyhow!("multi channel container has no data"))?;
+    if format.block_size == 0 {
+        return Err(anyhow!("multi channel container has a block size of 0"));
+    }
+
+    let mut channels: Vec<StreamChannel> = format
+        .channels
+        .iter()
+        .map(|c| StreamChannel {
+            name: format!("hash_{:08X}", c.id),
+            sample_rate: c.sample_rate,
+            headroom: c.headroom,
+            codec: c.codec,
+            // The sample count comes from the file, it isn't trusted for the allocation
+            samples: vec![],
+        })
+        .collect();
+    let channel_count = channels.len();
+
+    for block in data.chunks(format.block_size as usize) {
+        let mut entry_counts = vec![];
+        let mut sample_counts = vec![];
+        for channel in 0..channel_count {
+            let info = channel * BLOCK_CHANNEL_INFO_SIZE;
+            entry_counts.push(read_u32(block, info + 4)? as usize);
+            sample_counts.push(read_u32(block, info + 12)? as usize);
+        }
+
+        let seek_entries: usize = entry_counts.iter().sum();
+        let mut offset = align(
+            channel_count * BLOCK_CHANNEL_INFO_SIZE + seek_entries * 4,
+            BLOCK_HEADER_ALIGNMENT,
+        );
+        for (channel, (entry_count, sample_count)) in channels
+            .iter_mut()
+            .zip(entry_counts.into_iter().zip(sample_counts))
+        {
+            let size = entry_count * FRAME_SIZE;
+            let frames = block
+                .get(offset..offset + size)
+                .ok_or(anyhow!("unexpected end of stream block"))?;
+            let mut remaining = sample_count;
+            for frame in frames.chunks(FRAME_SIZE) {
+                let frame_samples = remaining.min(channel.samples_per_frame());
+                channel
+                    .samples
+                    .extend(channel.codec.decode(frame, frame_samples));
+                remaining -= frame_samples;
+            }
+            offset += size;
+        }
+    }
+
+    Ok(channels)
+}
+
+/// Builds a multi channel container where the data of all channels is interleaved in blocks.
+///
+/// Every block starts with a header containing per channel the index of its first frame, the
+/// amount of frames in the block, the samples to skip and the amount of samples in the block,
+/// followed by the start sample of each of those frames. The header is padded to 0x800 bytes
+/// after which the frames of each channel follow each other.
+pub fn build(channels: Vec<StreamChannel>) -> Result<Container> {
+    if channels.is_empty() {
+        return Err(anyhow!("A streamed container needs at least one channel"));
+    }
+    let codec = channels[0].codec;
+    if channels.iter().any(|c| c.codec != codec) {
+        return Err(anyhow!(
+            "All channels of a streamed container must use the same codec"
+        ));
+    }
+
+    let samples_per_frame = channels[0].samples_per_frame();
+    let frames_per_block = frames_per_block(channels.len());
+    if frames_per_block == 0 {
+        return Err(anyhow!(
+            "{} channels don't fit in the blocks of a streamed container",
+            channels.len()
+        ));
+    }
+    let frames: Vec<Vec<Vec<u8>>> = channels.iter().map(|c| c.encode_frames()).collect();
+    let max_frames = frames.iter().map(|f| f.len()).max().unwrap_or(0);
+    let block_count = max_frames.div_ceil(frames_per_block);
+
+    let mut data = vec![];
+    let mut seek_table = vec![];
+    for block in 0..block_count {
+        let first_frame = block * frames_per_block;
+        // Blocks are read at fixed offsets, only the last block can be shorter
+        data.resize(block * BLOCK_SIZE, 0);
+        let block_start = data.len();
+        seek_table.push((first_frame * samples_per_frame) as u32);
+
+        for (channel, channel_frames) in channels.iter().zip(&frames) {
+            let entry_count = channel_frames
+                .len()
+                .saturating_sub(first_frame)
+                .min(frames_per_block);
+            let sample_count = channel
+                .samples
+                .len()
+                .saturating_sub(first_frame * samples_per_frame)
+                .min(entry_count * samples_per_frame);
+            data.extend_from_slice(&(first_frame as u32).to_le_bytes());
+            data.extend_from_slice(&(entry_count as u32).to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&(sample_count as u32).to_le_bytes());
+        }
+
+        for channel_frames in &frames {
+            let entry_count = channel_frames
+                .len()
+                .saturating_sub(first_frame)
+                .min(frames_per_block);
+            for frame in first_frame..first_frame + entry_count {
+                data.extend_from_slice(&((frame * samples_per_frame) as u32).to_le_bytes());
+            }
+        }
+        data.resize(
+            block_start + align(data.len() - block_start, BLOCK_HEADER_ALIGNMENT),
+            0,
+        );
+
+        for channel_frames in &frames {
+            for frame in channel_frames
+                .iter()
+                .skip(first_frame)
+                .take(frames_per_block)
+            {
+                data.extend_from_slice(frame);
+            }
+        }
+    }
+
+    let mut streams = vec![Stream::multi_channel_header(vec![
+        Chunk::StreamFormat(StreamFormatChunk {
+            block_count: block_count as u32,
+            block_size: BLOCK_SIZE as u32,
+            channels: channels
+                .iter()
+                .map(|c| StreamFormatChannel {
+                    id: container::stream_id(&c.name),
+                    samples: c.samples.len() as u32,
+                    headroom: c.headroom,
+                    sample_rate: c.sample_rate,
+                    codec: c.codec,
+                })
+                .collect(),
+        }),
+        Chunk::Data(data),
+        Chunk::SeekTable(seek_table),
+    ])];
+    for channel in &channels {
+        let peaks = Peaks::from_samples(&channel.samples);
+        streams.push(Stream::new(&channel.name, vec![Chunk::Peak(peaks.blocks)]));
+    }
+
+    Ok(Container {
+        chunk_indices: true,
+        multi_channel: true,
+        streams,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, sample_count: usize) -> StreamChannel {
+        StreamChannel {
+            name: name.to_string(),
+            sample_rate: 32000,
+            headroom: -161,
+            codec: Codec::Pcm,
+            samples: (0..sample_count)
+                .map(|i| (i * 7 % 4000) as i16 - 2000)
+                .collect(),
+        }
+    }
+
+    fn block_u32s(block: &[u8], count: usize) -> Vec<u32> {
+        (0..count)
+            .map(|i| read_u32(block, i * 4).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn build_round_trip() {
+        let samples_per_frame = FRAME_SIZE / 2;
+        let per_block = frames_per_block(2);
+        // The left channel spills 2 frames into a second block, the last one partly filled
+        let left_samples = (per_block + 1) * samples_per_frame + 100;
+        let right_samples = 3 * samples_per_frame;
+        let bytes = build(vec![
+            channel("left", left_samples),
+            channel("right", right_samples),
+        ])
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+        let container = Container::parse(&bytes).unwrap();
+
+        let header = container.streams.iter().find(|s| s.id == 0).unwrap();
+        let format = header.stream_format().unwrap();
+        assert_eq!(format.block_count, 2);
+        assert_eq!(format.block_size, BLOCK_SIZE as u32);
+        let seek_table = header.chunks.iter().find_map(|c| match c {
+            Chunk::SeekTable(table) => Some(table.clone()),
+            _ => None,
+        });
+        assert_eq!(
+            seek_table,
+            Some(vec![0, (per_block * samples_per_frame) as u32])
+        );
+
+        let data = header.data().unwrap();
+        let (first, second) = data.split_at(BLOCK_SIZE);
+        // Channel infos: first frame, frame count, samples to skip and sample count
+        let per_block_samples = (per_block * samples_per_frame) as u32;
+        assert_eq!(
+            block_u32s(first, 8),
+            vec![
+                0,
+                per_block as u32,
+                0,
+                per_block_samples,
+                0,
+                3,
+                0,
+                right_samples as u32
+            ]
+        );
+        let seek_entries = block_u32s(&first[32..], per_block + 3);
+        assert_eq!(seek_entries[1], samples_per_frame as u32);
+        let frame_starts: Vec<u32> = (0..3).map(|f| (f * samples_per_frame) as u32).collect();
+        assert_eq!(&seek_entries[per_block..], frame_starts.as_slice());
+        let remaining = left_samples as u32 - per_block_samples;
+        assert_eq!(
+            block_u32s(second, 10),
+            vec![
+                per_block as u32,
+                2,
+                0,
+                remaining,
+                per_block as u32,
+                0,
+                0,
+                0,
+                per_block_samples,
+                per_block_samples + samples_per_frame as u32,
+            ]
+        );
+        // The last frame is padded with silence after its 100 samples
+        let last_frame = &second[BLOCK_HEADER_ALIGNMENT + FRAME_SIZE..][..FRAME_SIZE];
+        assert!(last_frame[200..].iter().all(|b| *b == 0));
+        assert_eq!(second.len(), BLOCK_HEADER_ALIGNMENT + 2 * FRAME_SIZE);
+
+        let channels = read_channels(&container).unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(
+            channels[0].name,
+            format!("hash_{:08X}", container::stream_id("left"))
+        );
+        assert_eq!(channels[0].samples, channel("left", left_samples).samples);
+        assert_eq!(channels[1].samples, channel("right", right_samples).samples);
+    }
+
+    #[test]
+    fn too_many_channels_is_an_error() {
+        let channels = (0..300).map(|i| channel(&format!("ch{}", i), 10)).collect();
+        assert!(build(channels).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::hash::Hash;
+
+// Known vanilla names, shipped with the binary
+const BUILTIN_CATALOGUE: &str = include_str!("catalogue.json");
+// Optional file in the project folder, its entries are added to (or replace) the builtin ones
+const PROJECT_CATALOGUE: &str = "catalogue.json";
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CatalogueEntry {
+    pub name: String,
+    // Only needed when the name isn't the one the hash was computed from
+    #[serde(default)]
+    hash: Option<Hash>,
+}
+
+impl CatalogueEntry {
+    pub fn hash(&self) -> Hash {
+        self.hash
+            .clone()
+            .unwrap_or_else(|| Hash::from_name(&self.name))
+    }
+}
+
+/// Names of the categories and volume curves the header editor can pick from
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Catalogue {
+    #[serde(default)]
+    pub categories: Vec<CatalogueEntry>,
+    #[serde(default)]
+    pub volume_curves: Vec<CatalogueEntry>,
+}
+
+impl Default for Catalogue {
+    fn default() -> Self {
+        serde_json::from_str(BUILTIN_CATALOGUE).expect("the builtin catalogue is valid json")
+    }
+}
+
+impl Catalogue {
+    /// Builtin catalogue extended with the `catalogue.json` of the project, if there is one
+    pub fn load(proj_loc: &Path) -> Result<Catalogue> {
+        let mut catalogue = Catalogue::default();
+        let path = proj_loc.join(PROJECT_CATALOGUE);
+        if path.exists() {
+            let overrides: Catalogue = serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+            merge(&mut catalogue.categories, overrides.categories);
+            merge(&mut catalogue.volume_curves, overrides.volume_curves);
+        }
+        Ok(catalogue)
+    }
+}
+
+fn merge(entries: &mut Vec<CatalogueEntry>, overrides: Vec<CatalogueEntry>) {
+    for entry in overrides {
+        let hash = entry.hash();
+        match entries.iter_mut().find(|e| e.hash() == hash) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+    }
+}
+
+/// Name of the entry matching the hash
+pub fn find_name<'a>(entries: &'a [CatalogueEntry], hash: &Hash) -> Option<&'a str> {
+    entries
+        .iter()
+        .find(|e| e.hash() == *hash)
+        .map(|e| e.name.as_str())
+}
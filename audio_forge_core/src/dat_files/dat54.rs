@@ -0,0 +1,146 @@
+use super::rel::RelItem;
+use crate::utils::hash::{joaat, name_hash, Hash};
+
+// Type of the rel files holding sounds
+pub const REL_TYPE: u32 = 54;
+// Version of the dat54 files shipped with the game
+pub const VERSION: u32 = 7314721;
+const LOOPING_SOUND_TYPE: u8 = 1;
+const SIMPLE_SOUND_TYPE: u8 = 12;
+
+// Bits of the header flags, a set bit means the matching field is present in the header
+pub const FLAG_VOLUME: u32 = 1 << 2;
+pub const FLAG_ATTACK_TIME: u32 = 1 << 12;
+pub const FLAG_RELEASE_TIME: u32 = 1 << 13;
+pub const FLAG_DOPPLER_FACTOR: u32 = 1 << 14;
+pub const FLAG_CATEGORY: u32 = 1 << 15;
+pub const FLAG_VOLUME_CURVE: u32 = 1 << 20;
+pub const FLAG_VOLUME_CURVE_DISTANCE: u32 = 1 << 21;
+pub const FLAG_UNK20: u32 = 1 << 24;
+pub const FLAG_ECHO_X: u32 = 1 << 25;
+pub const FLAG_ECHO_Y: u32 = 1 << 26;
+pub const FLAG_ECHO_Z: u32 = 1 << 27;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Header {
+    pub volume: u16,        // in dB
+    pub volume_curve: Hash, // distance attenuation curves
+    pub volume_curve_distance: u16,
+    pub doppler_factor: Option<u16>,
+    pub category: Hash,
+    pub attack_time: Option<u16>,  // Fade-in time
+    pub release_time: Option<u16>, // Fade-out time
+    pub unk20: u8,                 // VirtualiseAsGroup - Stereo panning L-R?
+    // - 0 = stereo
+    // - 1 = left
+    // - 2 = right
+    // - 3 = both
+    // - 4 = ? both + centre?
+    // - 5 =
+    // - 6 =
+    pub echo_x: Option<u16>,
+    pub echo_y: Option<u16>,
+    pub echo_z: Option<u16>,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            volume_curve: Hash::from_value(0xC2770146),
+            volume_curve_distance: 5,
+            category: Hash::from_value(0x02C7B342),
+            doppler_factor: None,
+            attack_time: None,
+            release_time: None,
+            unk20: 0,
+            echo_x: None,
+            echo_y: None,
+            echo_z: None,
+        }
+    }
+}
+
+impl Header {
+    pub fn flags(&self) -> u32 {
+        let mut flags = FLAG_VOLUME
+            | FLAG_CATEGORY
+            | FLAG_VOLUME_CURVE
+            | FLAG_VOLUME_CURVE_DISTANCE
+            | FLAG_UNK20;
+        for (field, flag) in [
+            (self.doppler_factor, FLAG_DOPPLER_FACTOR),
+            (self.attack_time, FLAG_ATTACK_TIME),
+            (self.release_time, FLAG_RELEASE_TIME),
+            (self.echo_x, FLAG_ECHO_X),
+            (self.echo_y, FLAG_ECHO_Y),
+            (self.echo_z, FLAG_ECHO_Z),
+        ] {
+            if field.is_some() {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+
+    /// Writes the flags followed by the fields of the set flags, in bit order
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.flags().to_le_bytes());
+        buf.extend_from_slice(&self.volume.to_le_bytes());
+        for value in [self.attack_time, self.release_time, self.doppler_factor]
+            .into_iter()
+            .flatten()
+        {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        buf.extend_from_slice(&self.category.value().to_le_bytes());
+        buf.extend_from_slice(&self.volume_curve.value().to_le_bytes());
+        buf.extend_from_slice(&self.volume_curve_distance.to_le_bytes());
+        buf.push(self.unk20);
+        for value in [self.echo_x, self.echo_y, self.echo_z]
+            .into_iter()
+            .flatten()
+        {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// SimpleSound playing `file_name` from the wave container at `container_path`
+pub fn simple_sound(name: &str, header: &Header, container_path: &str, file_name: &str) -> RelItem {
+    let mut data = vec![SIMPLE_SOUND_TYPE];
+    header.write_to(&mut data);
+    let container_offset = data.len();
+    data.extend_from_slice(&joaat(container_path).to_le_bytes());
+    data.extend_from_slice(&name_hash(file_name).to_le_bytes());
+    // Wave slot
+    data.push(0);
+
+    RelItem {
+        name: name.to_string(),
+        data,
+        hash_offsets: vec![],
+        pack_offsets: vec![container_offset],
+    }
+}
+
+/// LoopingSound repeating the sound `child` until it is stopped
+pub fn looping_sound(name: &str, header: &Header, child: &str) -> RelItem {
+    let mut data = vec![LOOPING_SOUND_TYPE];
+    header.write_to(&mut data);
+    // Loop count (-1 loops forever), its variance and the loop point
+    data.extend_from_slice(&(-1i16).to_le_bytes());
+    data.extend_from_slice(&0i16.to_le_bytes());
+    data.extend_from_slice(&0i16.to_le_bytes());
+    let child_offset = data.len();
+    data.extend_from_slice(&joaat(child).to_le_bytes());
+    // No game variable overrides the loop count
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    RelItem {
+        name: name.to_string(),
+        data,
+        hash_offsets: vec![child_offset],
+        pack_offsets: vec![],
+    }
+}
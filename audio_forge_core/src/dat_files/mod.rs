@@ -0,0 +1,3 @@
+pub mod catalogue;
+pub mod dat54;
+pub mod rel;
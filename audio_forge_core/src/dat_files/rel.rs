@@ -0,0 +1,209 @@
+use std::{fs::File, io::Write, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::hash::joaat;
+
+// Size of the rel type and data length fields in front of the data block
+const DATA_BLOCK_OFFSET: usize = 8;
+
+/// An item in the data block of a rel file
+pub struct RelItem {
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Offsets (relative to the start of the item) of hashes referencing other rel items
+    pub hash_offsets: Vec<usize>,
+    /// Offsets (relative to the start of the item) of hashes referencing wave containers
+    pub pack_offsets: Vec<usize>,
+}
+
+/// Compiled `.rel` file as loaded by the game.
+///
+/// Layout: rel type, data block (version followed by the items), name table (the container
+/// paths), index (name hash, offset and length of every item), hash table and pack table
+/// (file offsets of the hashes the game has to resolve on load).
+pub struct RelFile {
+    pub rel_type: u32,
+    pub version: u32,
+    pub name_table: Vec<String>,
+    pub items: Vec<RelItem>,
+}
+
+impl RelFile {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = self.version.to_le_bytes().to_vec();
+        let mut index = vec![];
+        let mut hash_table = vec![];
+        let mut pack_table = vec![];
+        for item in &self.items {
+            let offset = data.len();
+            index.push((joaat(&item.name), offset as u32, item.data.len() as u32));
+            hash_table.extend(
+                item.hash_offsets
+                    .iter()
+                    .map(|o| (DATA_BLOCK_OFFSET + offset + o) as u32),
+            );
+            pack_table.extend(
+                item.pack_offsets
+                    .iter()
+                    .map(|o| (DATA_BLOCK_OFFSET + offset + o) as u32),
+            );
+            data.extend_from_slice(&item.data);
+        }
+        // The game does a binary search on the index
+        index.sort_by_key(|(hash, _, _)| *hash);
+        if index.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(anyhow!("Rel file contains items with the same name hash"));
+        }
+
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.rel_type.to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&data);
+
+        let mut names = vec![];
+        let mut name_offsets = vec![];
+        for name in &self.name_table {
+            name_offsets.push(names.len() as u32);
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        buf.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(name_offsets.len() as u32).to_le_bytes());
+        for offset in name_offsets {
+            buf.extend_from_slice(&offset.to_le_bytes());
+        }
+        buf.extend_from_slice(&names);
+
+        buf.extend_from_slice(&(index.len() as u32).to_le_bytes());
+        for (hash, offset, length) in index {
+            buf.extend_from_slice(&hash.to_le_bytes());
+            buf.extend_from_slice(&offset.to_le_bytes());
+            buf.extend_from_slice(&length.to_le_bytes());
+        }
+
+        for table in [hash_table, pack_table] {
+            buf.extend_from_slice(&(table.len() as u32).to_le_bytes());
+            for offset in table {
+                buf.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Item names separated by null bytes, used by tools to turn the index hashes back into names
+    pub fn name_table_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        for item in &self.items {
+            buf.extend_from_slice(item.name.as_bytes());
+            buf.push(0);
+        }
+        buf
+    }
+
+    /// Writes the rel file and its `.nametable` next to it
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(&self.to_bytes()?)?;
+
+        let mut f = File::create(path.with_extension("nametable"))?;
+        f.write_all(&self.name_table_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat_files::dat54;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn dat54_file() -> RelFile {
+        let header = dat54::Header {
+            attack_time: Some(10),
+            ..dat54::Header::default()
+        };
+        RelFile {
+            rel_type: dat54::REL_TYPE,
+            version: dat54::VERSION,
+            name_table: vec![String::from("audio_forge/n10")],
+            items: vec![
+                dat54::simple_sound("boom", &header, "audio_forge/n10", "boom"),
+                dat54::looping_sound("boom_loop", &dat54::Header::default(), "boom"),
+            ],
+        }
+    }
+
+    #[test]
+    fn dat54_layout() {
+        let bytes = dat54_file().to_bytes().unwrap();
+
+        assert_eq!(u32_at(&bytes, 0), 54);
+        // Version, SimpleSound (29 bytes) and LoopingSound (32 bytes)
+        let data_len = u32_at(&bytes, 4) as usize;
+        assert_eq!(data_len, 4 + 29 + 32);
+        assert_eq!(u32_at(&bytes, 8), dat54::VERSION);
+
+        // Volume, attack time, category, volume curve, its distance and unk20
+        let simple = 12;
+        assert_eq!(bytes[simple], 12);
+        assert_eq!(u32_at(&bytes, simple + 1), 0x0130_9004);
+        assert_eq!(u32_at(&bytes, simple + 20), joaat("audio_forge/n10"));
+        assert_eq!(u32_at(&bytes, simple + 24), joaat("boom"));
+        let looping = simple + 29;
+        assert_eq!(bytes[looping], 1);
+        assert_eq!(u32_at(&bytes, looping + 1), 0x0130_8004);
+        assert_eq!(u32_at(&bytes, looping + 24), joaat("boom"));
+
+        let names = 8 + data_len;
+        assert_eq!(u32_at(&bytes, names), 16);
+        assert_eq!(u32_at(&bytes, names + 4), 1);
+        assert_eq!(u32_at(&bytes, names + 8), 0);
+        assert_eq!(&bytes[names + 12..names + 28], b"audio_forge/n10\0");
+
+        let index = names + 28;
+        assert_eq!(u32_at(&bytes, index), 2);
+        let entries: Vec<(u32, u32, u32)> = (0..2)
+            .map(|i| {
+                let entry = index + 4 + i * 12;
+                (
+                    u32_at(&bytes, entry),
+                    u32_at(&bytes, entry + 4),
+                    u32_at(&bytes, entry + 8),
+                )
+            })
+            .collect();
+        let mut expected = vec![(joaat("boom"), 4, 29), (joaat("boom_loop"), 33, 32)];
+        expected.sort();
+        assert_eq!(entries, expected);
+
+        // The child hash of the LoopingSound and the container hash of the SimpleSound
+        let tables = index + 4 + 2 * 12;
+        assert_eq!(u32_at(&bytes, tables), 1);
+        assert_eq!(u32_at(&bytes, tables + 4) as usize, looping + 24);
+        assert_eq!(u32_at(&bytes, tables + 8), 1);
+        assert_eq!(u32_at(&bytes, tables + 12) as usize, simple + 20);
+        assert_eq!(bytes.len(), tables + 16);
+    }
+
+    #[test]
+    fn name_table_lists_the_items() {
+        assert_eq!(dat54_file().name_table_bytes(), b"boom\0boom_loop\0");
+    }
+
+    #[test]
+    fn duplicate_names_are_an_error() {
+        let mut rel = dat54_file();
+        rel.items.push(dat54::simple_sound(
+            "boom",
+            &dat54::Header::default(),
+            "audio_forge/n10",
+            "boom",
+        ));
+        assert!(rel.to_bytes().is_err());
+    }
+}
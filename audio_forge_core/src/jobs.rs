@@ -0,0 +1,305 @@
+// Background jobs for the slow parts (transcoding, building packs).
+//
+// Jobs run on a small pool of worker threads. Each job gets a `JobContext` to report its progress
+// and to check whether it was cancelled, the frontend polls the queue for finished jobs and
+// applies their results.
+
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use log::error;
+
+/// Error returned by jobs which stopped because they were cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Handle given to a running job. The default context is never cancelled and its progress isn't
+/// read by anyone, which is what synchronous callers like the CLI use.
+#[derive(Clone)]
+pub struct JobContext {
+    // Progress as f32 bits, shared with the queue
+    progress: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+    // Part of the total progress this context reports into
+    start: f32,
+    end: f32,
+}
+
+impl Default for JobContext {
+    fn default() -> Self {
+        Self {
+            progress: Arc::new(AtomicU32::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            start: 0.0,
+            end: 1.0,
+        }
+    }
+}
+
+impl JobContext {
+    /// Sets the progress of this (part of the) job, from 0 to 1
+    pub fn set_progress(&self, fraction: f32) {
+        let progress = self.start + (self.end - self.start) * fraction.clamp(0.0, 1.0);
+        self.progress.store(progress.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Context for step `index` of `count` equally sized steps of this job
+    pub fn part(&self, index: usize, count: usize) -> JobContext {
+        let step = (self.end - self.start) / count.max(1) as f32;
+        JobContext {
+            progress: self.progress.clone(),
+            cancelled: self.cancelled.clone(),
+            start: self.start + step * index as f32,
+            end: self.start + step * (index + 1) as f32,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns a [`Cancelled`] error once the job was cancelled, long running loops should call
+    /// this regularly
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_done(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// A job as shown to the user
+pub struct Job {
+    pub id: JobId,
+    pub name: String,
+    pub status: JobStatus,
+    context: JobContext,
+}
+
+impl Job {
+    pub fn progress(&self) -> f32 {
+        match self.status {
+            JobStatus::Finished => 1.0,
+            _ => f32::from_bits(self.context.progress.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+type Work<T> = Box<dyn FnOnce(&JobContext) -> Result<T> + Send>;
+
+struct QueuedWork<T> {
+    id: JobId,
+    context: JobContext,
+    work: Work<T>,
+}
+
+enum Message<T> {
+    Started(JobId),
+    Done(JobId, Result<T>),
+}
+
+/// Runs jobs producing a `T` on worker threads
+pub struct JobQueue<T> {
+    sender: mpsc::Sender<QueuedWork<T>>,
+    messages: mpsc::Receiver<Message<T>>,
+    jobs: Vec<Job>,
+    next_id: JobId,
+}
+
+impl<T: Send + 'static> Default for JobQueue<T> {
+    fn default() -> Self {
+        let workers = thread::available_parallelism().map_or(2, |n| n.get().min(4));
+        Self::new(workers)
+    }
+}
+
+impl<T: Send + 'static> JobQueue<T> {
+    pub fn new(workers: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<QueuedWork<T>>();
+        let (message_sender, messages) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let message_sender = message_sender.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("job-worker-{}", i))
+                .spawn(move || worker(&receiver, &message_sender));
+            if let Err(err) = spawned {
+                error!("Failed to start job worker: {:?}", err);
+            }
+        }
+
+        Self {
+            sender,
+            messages,
+            jobs: vec![],
+            next_id: 0,
+        }
+    }
+
+    /// Queues `work`, `name` is shown to the user
+    pub fn spawn(
+        &mut self,
+        name: impl Into<String>,
+        work: impl FnOnce(&JobContext) -> Result<T> + Send + 'static,
+    ) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let context = JobContext::default();
+
+        self.jobs.push(Job {
+            id,
+            name: name.into(),
+            status: JobStatus::Queued,
+            context: context.clone(),
+        });
+        let queued = QueuedWork {
+            id,
+            context,
+            work: Box::new(work),
+        };
+        if self.sender.send(queued).is_err() {
+            self.set_status(
+                id,
+                JobStatus::Failed(String::from("No job workers are running")),
+            );
+        }
+        id
+    }
+
+    /// Asks the job to stop, queued jobs won't be started at all
+    pub fn cancel(&mut self, id: JobId) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+            job.context.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for job in &self.jobs {
+            job.context.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Updates the job statuses and returns the results of the jobs finished since the last call.
+    /// Results of cancelled jobs are dropped, even when the job finished anyway.
+    pub fn poll(&mut self) -> Vec<(JobId, T)> {
+        let mut finished = vec![];
+        while let Ok(message) = self.messages.try_recv() {
+            match message {
+                Message::Started(id) => self.set_status(id, JobStatus::Running),
+                Message::Done(id, result) => {
+                    let cancelled = self
+                        .jobs
+                        .iter()
+                        .any(|j| j.id == id && j.context.is_cancelled());
+                    match result {
+                        _ if cancelled => self.set_status(id, JobStatus::Cancelled),
+                        Ok(output) => {
+                            self.set_status(id, JobStatus::Finished);
+                            finished.push((id, output));
+                        }
+                        Err(err) if err.is::<Cancelled>() => {
+                            self.set_status(id, JobStatus::Cancelled)
+                        }
+                        Err(err) => {
+                            error!("Job {} failed: {:?}", id, err);
+                            self.set_status(id, JobStatus::Failed(format!("{:#}", err)));
+                        }
+                    }
+                }
+            }
+        }
+        finished
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Whether any job is still queued or running
+    pub fn is_busy(&self) -> bool {
+        self.jobs.iter().any(|j| !j.status.is_done())
+    }
+
+    /// Removes finished, failed and cancelled jobs from the list
+    pub fn clear_done(&mut self) {
+        self.jobs.retain(|j| !j.status.is_done());
+    }
+
+    fn set_status(&mut self, id: JobId, status: JobStatus) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+            job.status = status;
+        }
+    }
+}
+
+impl<T> Drop for JobQueue<T> {
+    fn drop(&mut self) {
+        // Workers exit once the channel is closed, running jobs are stopped early
+        for job in &self.jobs {
+            job.context.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn worker<T>(receiver: &Mutex<mpsc::Receiver<QueuedWork<T>>>, messages: &mpsc::Sender<Message<T>>) {
+    loop {
+        // Only hold the lock while waiting, so other workers can pick up jobs
+        let queued = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(queued) = queued else {
+            return;
+        };
+
+        let result = match queued.context.check_cancelled() {
+            Ok(()) => {
+                let _ = messages.send(Message::Started(queued.id));
+                // A panicking job shouldn't take the worker down with it
+                let context = queued.context;
+                let work = queued.work;
+                panic::catch_unwind(AssertUnwindSafe(move || work(&context)))
+                    .unwrap_or_else(|_| Err(anyhow!("The job panicked")))
+            }
+            Err(err) => Err(err),
+        };
+        if messages.send(Message::Done(queued.id, result)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Project model, importers and generators of AudioForge.
+//!
+//! The GUI and the command line interface are thin frontends over this crate:
+//! - [`project_mgmt`]: projects, awc packs and everything generated from them
+//! - [`awc_files`]: binary awc containers
+//! - [`dat_files`]: dat54 sound data
+//! - [`jobs`]: background jobs with progress and cancellation
+//! - [`utils`]: hashing, wav/ADPCM codecs, transcoding and XML helpers
+
+pub mod awc_files;
+pub mod dat_files;
+pub mod jobs;
+pub mod project_mgmt;
+pub mod utils;
+
+/// Initializes the libraries used for transcoding, call this once before using the crate
+pub fn init() -> anyhow::Result<()> {
+    ffmpeg_next::init()?;
+    Ok(())
+}
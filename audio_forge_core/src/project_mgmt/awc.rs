@@ -0,0 +1,1087 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use strum::EnumIter;
+
+use crate::{
+    awc_files::{container, streamed},
+    dat_files::dat54,
+    jobs::JobContext,
+    utils::{
+        adpcm, fs as fs_utils,
+        hash::{self, Hash},
+        transcoder::{self, ChannelOutput, ChannelSource},
+        wav, xml,
+    },
+};
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone)]
+pub struct AwcPack {
+    pub name: String,
+    pub pack_type: AwcPackType,
+    pub entries: Vec<AwcEntry>,
+}
+
+impl PartialOrd for AwcPack {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.name.cmp(&other.name))
+    }
+}
+
+impl Ord for AwcPack {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl AwcPack {
+    pub fn add_entry(
+        &mut self,
+        proj_path: &PathBuf,
+        entry_path: &Path,
+        entry_name: &str,
+    ) -> anyhow::Result<()> {
+        let entry = AwcEntry::from_file(
+            proj_path,
+            entry_path,
+            entry_name,
+            self.pack_type.default_codec(),
+            &JobContext::default(),
+        )?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    // Directory where the generated per-channel wav files of this pack are stored
+    pub fn build_dir(&self, proj_loc: &Path) -> PathBuf {
+        proj_loc.join("output/awc/.packs/").join(&self.name)
+    }
+
+    pub fn estimated_size(&self) -> usize {
+        self.entries.iter().map(|e| e.estimated_size()).sum()
+    }
+
+    // Path of the container holding the entry as referenced by the game data,
+    // `<wavepack directory>/<awc name>`
+    pub fn container_path(&self, entry: &AwcEntry) -> String {
+        match self.pack_type {
+            AwcPackType::Simple => format!("{}/{}", self.name, self.name),
+            AwcPackType::Radio => format!("{}/{}", self.name, entry.name),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct AwcEntry {
+    path: PathBuf,
+    pub name: String,
+    pub looped: bool,
+    // Entries created before the codec was configurable were always declared as ADPCM
+    #[serde(default)]
+    pub codec: AwcCodec,
+    // Sample where playback restarts when the entry is looped
+    #[serde(default)]
+    pub loop_start: u64,
+    // Sample after the loop region, the end of the play range when unset
+    #[serde(default)]
+    pub loop_end: Option<u64>,
+    // Samples outside of the play range are cut off when building, the end of the file when unset
+    #[serde(default)]
+    pub play_begin: u64,
+    #[serde(default)]
+    pub play_end: Option<u64>,
+    #[serde(default)]
+    pub markers: Vec<AwcMarker>,
+    pub headers: dat54::Header,
+    // Retrieved from FFMPEG
+    sample_rate: u32,
+    // Per channel, counted from the wav file
+    samples: u64,
+    // Channel count of the source file
+    channels: u16,
+    pub channel_mode: ChannelMode,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct AwcMarker {
+    pub name: u32,
+    pub value: u32,
+    pub sample_offset: u32,
+}
+
+impl From<&container::Marker> for AwcMarker {
+    fn from(marker: &container::Marker) -> Self {
+        Self {
+            name: marker.name,
+            value: marker.value,
+            sample_offset: marker.sample_offset,
+        }
+    }
+}
+
+impl From<&AwcMarker> for container::Marker {
+    fn from(marker: &AwcMarker) -> Self {
+        Self {
+            name: marker.name,
+            value: marker.value,
+            sample_offset: marker.sample_offset,
+        }
+    }
+}
+
+impl AwcEntry {
+    pub fn from_file(
+        proj_path: &PathBuf,
+        entry_path: &Path,
+        entry_name: &str,
+        codec: AwcCodec,
+        job: &JobContext,
+    ) -> Result<AwcEntry> {
+        let entry_path = entry_path.join(format!("{}.wav", entry_name));
+        let info = transcoder::probe(&entry_path)?;
+        let samples = transcoder::count_samples(&entry_path, job)?;
+
+        let rel_path_res = entry_path.strip_prefix(proj_path);
+        if rel_path_res.is_err() {
+            return Err(anyhow!(
+                "Given entry files are not stored in the project directory"
+            ));
+        }
+
+        let rel_path = rel_path_res.unwrap();
+
+        Ok(AwcEntry::new(
+            rel_path.to_path_buf(),
+            entry_name,
+            codec,
+            info.sample_rate,
+            samples,
+            info.channels,
+        ))
+    }
+
+    pub fn new(
+        rel_path: PathBuf,
+        name: &str,
+        codec: AwcCodec,
+        sample_rate: u32,
+        samples: u64,
+        channels: u16,
+    ) -> AwcEntry {
+        AwcEntry {
+            path: rel_path,
+            name: name.to_string(),
+            looped: false,
+            codec,
+            loop_start: 0,
+            loop_end: None,
+            play_begin: 0,
+            play_end: None,
+            markers: vec![],
+            headers: dat54::Header::default(),
+            sample_rate,
+            samples,
+            channels,
+            channel_mode: ChannelMode::for_channels(channels),
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn set_samples(&mut self, samples: u64) {
+        self.samples = samples;
+    }
+
+    /// Play range and loop region with unset ends resolved, clamped to the file so that the loop
+    /// region is inside of the play range
+    pub fn region(&self) -> AwcRegion {
+        let play_end = self.play_end.unwrap_or(self.samples).min(self.samples);
+        let play_begin = self.play_begin.min(play_end);
+        let loop_end = self
+            .loop_end
+            .unwrap_or(play_end)
+            .clamp(play_begin, play_end);
+        let loop_start = self.loop_start.clamp(play_begin, loop_end);
+        AwcRegion {
+            play_begin,
+            play_end,
+            loop_start,
+            loop_end,
+        }
+    }
+
+    /// Stores the region, ends at the end of the file (or play range) are stored as unset so they
+    /// follow when the sample count changes
+    pub fn set_region(&mut self, region: AwcRegion) {
+        self.play_begin = region.play_begin;
+        self.play_end = (region.play_end < self.samples).then_some(region.play_end);
+        self.loop_start = region.loop_start;
+        self.loop_end = (region.loop_end < region.play_end).then_some(region.loop_end);
+    }
+
+    /// Takes the play range and loop region from the fields of a format chunk, the inverse of
+    /// [`AwcEntry::format_region`]
+    pub fn set_format_region(&mut self, format: &FormatRegion) {
+        let play_begin = u64::from(format.play_begin).min(self.samples);
+        let play_end = self
+            .samples
+            .saturating_sub(format.play_end.into())
+            .max(play_begin);
+        self.looped = format.loop_point >= 0;
+        // Game files don't always fill in the loop begin, the loop point is the absolute start
+        let loop_start = match self.looped {
+            true => format.loop_point as u64,
+            false => play_begin,
+        };
+        let loop_end = match self.looped {
+            true => play_end.saturating_sub(format.loop_end.into()),
+            false => play_end,
+        };
+        self.set_region(AwcRegion {
+            play_begin,
+            play_end,
+            loop_start: loop_start.clamp(play_begin, play_end),
+            loop_end: loop_end.clamp(play_begin, play_end),
+        });
+    }
+
+    /// Takes the loop region and markers from the cue points of the source wav
+    pub fn apply_wav_cues(&mut self, cues: &wav::WavCues) {
+        if let Some((start, end)) = cues.sample_loop {
+            let end = end.min(self.samples);
+            self.looped = true;
+            self.loop_start = start.min(end);
+            self.loop_end = (end < self.samples).then_some(end);
+        }
+        // Marker offsets are 32 bits, cues past that can't be stored
+        self.markers = cues
+            .cues
+            .iter()
+            .filter_map(|cue| {
+                Some(AwcMarker {
+                    name: hash::joaat(cue.label.as_deref().unwrap_or("cue")),
+                    value: cue.id,
+                    sample_offset: u32::try_from(cue.position).ok()?,
+                })
+            })
+            .collect();
+    }
+
+    /// Play range and loop region as written to the format chunk, fails when they don't fit its
+    /// fields
+    pub fn format_region(&self) -> Result<FormatRegion> {
+        let region = self.region();
+        let field = |value: u64, what: &str| {
+            u16::try_from(value).map_err(|_| {
+                anyhow!(
+                    "{}: the {} is {} samples, the format chunk can store at most {}",
+                    self.name,
+                    what,
+                    value,
+                    u16::MAX
+                )
+            })
+        };
+
+        let play_begin = u8::try_from(region.play_begin).map_err(|_| {
+            anyhow!(
+                "{}: the play range begins at sample {}, the format chunk can skip at most {} samples",
+                self.name,
+                region.play_begin,
+                u8::MAX
+            )
+        })?;
+        let play_end = field(self.samples - region.play_end, "cut after the play range")?;
+        if !self.looped {
+            return Ok(FormatRegion {
+                loop_point: -1,
+                play_begin,
+                play_end,
+                loop_begin: 0,
+                loop_end: 0,
+            });
+        }
+
+        // An empty loop region loops the whole play range
+        let (loop_start, loop_end) = match region.loop_start < region.loop_end {
+            true => (region.loop_start, region.loop_end),
+            false => (region.play_begin, region.play_end),
+        };
+        Ok(FormatRegion {
+            loop_point: i32::try_from(loop_start)?,
+            play_begin,
+            play_end,
+            loop_begin: field(
+                loop_start - region.play_begin,
+                "loop start in the play range",
+            )?,
+            loop_end: field(region.play_end - loop_end, "part after the loop")?,
+        })
+    }
+
+    // Markers are only written when they are inside of the play range
+    fn output_markers(&self) -> Vec<&AwcMarker> {
+        let region = self.region();
+        self.markers
+            .iter()
+            .filter(|m| (region.play_begin..region.play_end).contains(&m.sample_offset.into()))
+            .collect()
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Records the channel count of the source file and picks the matching channel mode
+    pub fn set_channels(&mut self, channels: u16) {
+        self.channels = channels;
+        self.channel_mode = ChannelMode::for_channels(channels);
+    }
+
+    /// Counts the samples of the wav file of the entry
+    pub fn count_samples(&self, proj_loc: &Path, job: &JobContext) -> Result<u64> {
+        Ok(transcoder::count_samples(&self.source_path(proj_loc), job)?)
+    }
+
+    // Size of the stream data of all channels in the generated awc
+    pub fn estimated_size(&self) -> usize {
+        let samples = self.samples as usize;
+        let channel_size = match self.codec {
+            AwcCodec::Pcm => samples * 2,
+            AwcCodec::Adpcm => adpcm::encoded_size(samples),
+        };
+        channel_size * self.output_channels().len()
+    }
+
+    fn channel_stream_name(&self, channel: &str) -> String {
+        format!("{}_{}", self.name, channel)
+    }
+
+    // Name and source channel of every stream generated for the entry
+    fn output_channels(&self) -> Vec<(String, ChannelSource)> {
+        match self.channel_mode {
+            ChannelMode::KeepMono => vec![(self.name.clone(), ChannelSource::Channel(0))],
+            ChannelMode::DownmixMono => vec![(self.name.clone(), ChannelSource::Downmix)],
+            ChannelMode::SplitStereo => vec![
+                (self.channel_stream_name("left"), ChannelSource::Channel(0)),
+                (self.channel_stream_name("right"), ChannelSource::Channel(1)),
+            ],
+            ChannelMode::SplitAll => (0..self.channels.max(1) as usize)
+                .map(|i| {
+                    (
+                        self.channel_stream_name(&format!("ch{}", i)),
+                        ChannelSource::Channel(i),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn stream_names(&self) -> Vec<String> {
+        self.output_channels()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Wav file of the entry as stored in the project
+    pub fn source_path(&self, proj_loc: &Path) -> PathBuf {
+        proj_loc.join(&self.path)
+    }
+
+    // The XML references the channel files relative to the pack build directory
+    pub fn channel_wav_path(&self, build_dir: &Path, stream_name: &str) -> PathBuf {
+        build_dir.join(format!("{}.wav", stream_name))
+    }
+
+    // Remembers the channel mode the wav files in the build directory were split with, mono
+    // modes write the same file names
+    fn split_stamp_path(&self, build_dir: &Path) -> PathBuf {
+        build_dir.join(format!(".{}.split", self.name))
+    }
+
+    pub fn generate_splitted_variant(
+        &self,
+        proj_loc: &Path,
+        build_dir: &Path,
+        job: &JobContext,
+    ) -> Result<()> {
+        let file_path = self.source_path(proj_loc);
+        let channels = self.output_channels();
+        let paths: Vec<PathBuf> = channels
+            .iter()
+            .map(|(name, _)| self.channel_wav_path(build_dir, name))
+            .collect();
+        let stamp_path = self.split_stamp_path(build_dir);
+        let stamp = format!("{:?}", self.channel_mode);
+
+        // Unchanged sources were already split by a previous build
+        let outputs: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        if fs_utils::is_up_to_date(&outputs, &file_path)
+            && fs::read_to_string(&stamp_path).is_ok_and(|s| s == stamp)
+        {
+            return Ok(());
+        }
+
+        fs::create_dir_all(build_dir)?;
+        let mut buffers = vec![vec![]; channels.len()];
+        let mut outputs: Vec<ChannelOutput> = channels
+            .iter()
+            .zip(buffers.iter_mut())
+            .map(|((_, source), samples)| ChannelOutput {
+                source: *source,
+                samples,
+            })
+            .collect();
+        let sample_rate = transcoder::split_channels(&file_path, &mut outputs, job)?;
+
+        for (path, samples) in paths.iter().zip(buffers) {
+            let wav = wav::Wav {
+                channels: 1,
+                sample_rate,
+                samples,
+            };
+            fs_utils::write_atomic(path, &wav.to_bytes())?;
+        }
+        fs::write(&stamp_path, stamp)?;
+        Ok(())
+    }
+
+    fn read_channel_wav(&self, build_dir: &Path, stream_name: &str) -> Result<wav::Wav> {
+        let wav = wav::Wav::read(&self.channel_wav_path(build_dir, stream_name))?;
+        if wav.channels != 1 {
+            return Err(anyhow!(
+                "Expected a mono wav file for {}, got {} channels",
+                stream_name,
+                wav.channels
+            ));
+        }
+        Ok(wav)
+    }
+
+    pub fn to_binary_streams(&self, build_dir: &Path) -> Result<Vec<container::Stream>> {
+        let mut streams = vec![];
+        let region = self.format_region()?;
+
+        for (i, name) in self.stream_names().into_iter().enumerate() {
+            let wav = self.read_channel_wav(build_dir, &name)?;
+            let peaks = container::Peaks::from_samples(&wav.samples);
+
+            let format = container::FormatChunk {
+                samples: wav.sample_count().try_into()?,
+                loop_point: region.loop_point,
+                sample_rate: wav.sample_rate.try_into()?,
+                headroom: -161,
+                loop_begin: region.loop_begin,
+                loop_end: region.loop_end,
+                play_end: region.play_end,
+                play_begin: region.play_begin,
+                codec: self.codec.into(),
+                peak: peaks.overall,
+            };
+            let data = match self.codec {
+                AwcCodec::Pcm => wav.samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+                AwcCodec::Adpcm => adpcm::encode(&wav.samples),
+            };
+
+            let mut chunks = vec![
+                container::Chunk::Peak(peaks.blocks),
+                container::Chunk::Data(data),
+                container::Chunk::Format(format),
+            ];
+            // Markers only have to be triggered once for the whole entry
+            let markers = self.output_markers();
+            if i == 0 && !markers.is_empty() {
+                chunks.push(container::Chunk::Markers(
+                    markers.into_iter().map(container::Marker::from).collect(),
+                ));
+            }
+
+            streams.push(container::Stream::new(&name, chunks));
+        }
+
+        Ok(streams)
+    }
+
+    // Radio tracks are stored as a single multi channel stream per container
+    /// Multi channel containers have no fields for the play range, fails when the entry has one
+    pub fn check_whole_play_range(&self) -> Result<()> {
+        let region = self.region();
+        if region.play_begin > 0 || region.play_end < self.samples {
+            return Err(anyhow!(
+                "{} has a play range, radio tracks always play the whole file",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_streamed_container(&self, build_dir: &Path) -> Result<container::Container> {
+        self.check_whole_play_range()?;
+        let mut channels = vec![];
+
+        for name in self.stream_names() {
+            let wav = self.read_channel_wav(build_dir, &name)?;
+            channels.push(streamed::StreamChannel {
+                name,
+                sample_rate: wav.sample_rate.try_into()?,
+                headroom: -161,
+                codec: self.codec.into(),
+                samples: wav.samples,
+            });
+        }
+
+        streamed::build(channels)
+    }
+
+    pub fn to_streamed_xml(&self, build_dir: &Path) -> Result<AwcXML> {
+        self.check_whole_play_range()?;
+        let mut streams = vec![AwcStream {
+            name: xml::InlineValue::new("hash_00000000"),
+            file_name: None,
+            stream_format: None,
+            chunks: xml::ItemList {
+                item: vec![
+                    AwcChunk::StreamFormat(AwcStreamFormatChunk {
+                        block_size: xml::Value::new(streamed::BLOCK_SIZE as u32),
+                    }),
+                    AwcChunk::Data,
+                    AwcChunk::SeekTable,
+                ],
+            },
+        }];
+
+        for name in self.stream_names() {
+            let peaks =
+                container::Peaks::from_samples(&self.read_channel_wav(build_dir, &name)?.samples);
+            streams.push(AwcStream {
+                name: xml::InlineValue::new(&name),
+                file_name: Some(xml::InlineValue::new(&format!("{}.wav", &name))),
+                stream_format: Some(AwcStreamFormat {
+                    codec: xml::InlineValue::new(self.codec.xml_name()),
+                    samples: xml::Value::new(self.samples),
+                    sample_rate: xml::Value::new(self.sample_rate),
+                    headroom: xml::Value::new(-161),
+                }),
+                chunks: xml::ItemList {
+                    item: vec![AwcChunk::Peak(AwcPeakChunk::new(&peaks))],
+                },
+            });
+        }
+
+        Ok(AwcXML {
+            version: xml::Value::new(1),
+            chunk_indices: xml::Value::new("True".to_string()),
+            multi_channel: Some(xml::Value::new("True".to_string())),
+            streams: xml::ItemList { item: streams },
+        })
+    }
+
+    // The peaks are computed from the split wav files, they have to be generated first
+    pub fn to_xml_stream(&self, build_dir: &Path) -> Result<Vec<AwcStream>> {
+        let mut streams = vec![];
+        let region = self.format_region()?;
+
+        for (i, name) in self.stream_names().into_iter().enumerate() {
+            let peaks =
+                container::Peaks::from_samples(&self.read_channel_wav(build_dir, &name)?.samples);
+            let mut chunks = vec![
+                AwcChunk::Peak(AwcPeakChunk::new(&peaks)),
+                AwcChunk::Data,
+                AwcChunk::Format(AwcFormatChunk::new(
+                    self.samples,
+                    self.sample_rate,
+                    self.codec,
+                    &region,
+                    peaks.overall,
+                )),
+            ];
+            let markers = self.output_markers();
+            if i == 0 && !markers.is_empty() {
+                chunks.push(AwcChunk::Markers(AwcMarkersChunk {
+                    markers: xml::ItemList {
+                        item: markers.into_iter().map(AwcMarkerXml::from).collect(),
+                    },
+                }));
+            }
+
+            streams.push(AwcStream {
+                name: xml::InlineValue::new(&name),
+                file_name: Some(xml::InlineValue::new(&format!("{}.wav", &name))),
+                stream_format: None,
+                chunks: xml::ItemList { item: chunks },
+            });
+        }
+
+        Ok(streams)
+    }
+}
+
+#[derive(
+    Debug,
+    serde::Deserialize,
+    serde::Serialize,
+    Default,
+    PartialEq,
+    EnumIter,
+    Clone,
+    Copy,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+pub enum AwcPackType {
+    #[default]
+    Simple,
+    Radio,
+}
+
+/// Play range and loop region of an entry, in samples of the source file. The ends are
+/// exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AwcRegion {
+    pub play_begin: u64,
+    pub play_end: u64,
+    pub loop_start: u64,
+    pub loop_end: u64,
+}
+
+/// Play range and loop region in the fields of the format chunk. The play range is given by the
+/// samples skipped at the start and cut off at the end of the stream, the loop region by the
+/// samples between it and the ends of the play range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatRegion {
+    /// Sample of the stream where playback restarts, -1 when the entry isn't looped
+    pub loop_point: i32,
+    pub play_begin: u8,
+    pub play_end: u16,
+    pub loop_begin: u16,
+    pub loop_end: u16,
+}
+
+/// How the channels of the source file are turned into awc streams
+#[derive(
+    Debug,
+    serde::Deserialize,
+    serde::Serialize,
+    Default,
+    PartialEq,
+    EnumIter,
+    Clone,
+    Copy,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+pub enum ChannelMode {
+    // A single stream from the first channel
+    KeepMono,
+    // `_left` and `_right` streams
+    #[default]
+    SplitStereo,
+    // A single stream averaging all channels
+    DownmixMono,
+    // A `_ch<n>` stream for every channel
+    SplitAll,
+}
+
+impl ChannelMode {
+    /// Mode used for newly imported sources with `channels` channels
+    pub fn for_channels(channels: u16) -> ChannelMode {
+        match channels {
+            0 | 1 => ChannelMode::KeepMono,
+            2 => ChannelMode::SplitStereo,
+            _ => ChannelMode::SplitAll,
+        }
+    }
+}
+
+impl ToString for ChannelMode {
+    fn to_string(&self) -> String {
+        match self {
+            ChannelMode::KeepMono => String::from("Mono"),
+            ChannelMode::SplitStereo => String::from("Stereo (left/right)"),
+            ChannelMode::DownmixMono => String::from("Downmix to mono"),
+            ChannelMode::SplitAll => String::from("Split all channels"),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, EnumIter, Clone, Copy, Eq)]
+pub enum AwcOutputFormat {
+    #[default]
+    Binary,
+    // CodeWalker XML + wav files, can be imported in CodeWalker to create the .awc
+    Xml,
+}
+
+impl ToString for AwcOutputFormat {
+    fn to_string(&self) -> String {
+        match self {
+            AwcOutputFormat::Binary => String::from("Binary (.awc)"),
+            AwcOutputFormat::Xml => String::from("CodeWalker XML"),
+        }
+    }
+}
+
+impl AwcPackType {
+    pub fn default_codec(&self) -> AwcCodec {
+        match self {
+            // Mostly short sounds where ADPCM artifacts on transients are noticeable
+            AwcPackType::Simple => AwcCodec::Pcm,
+            AwcPackType::Radio => AwcCodec::Adpcm,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    serde::Deserialize,
+    serde::Serialize,
+    Default,
+    PartialEq,
+    EnumIter,
+    Clone,
+    Copy,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+pub enum AwcCodec {
+    Pcm,
+    #[default]
+    Adpcm,
+}
+
+impl AwcCodec {
+    // Codec name as used by CodeWalker
+    fn xml_name(&self) -> &str {
+        match self {
+            AwcCodec::Pcm => "PCM",
+            AwcCodec::Adpcm => "ADPCM",
+        }
+    }
+
+    fn from_xml_name(name: &str) -> Option<AwcCodec> {
+        match name {
+            "PCM" => Some(AwcCodec::Pcm),
+            "ADPCM" => Some(AwcCodec::Adpcm),
+            _ => None,
+        }
+    }
+}
+
+impl From<container::Codec> for AwcCodec {
+    fn from(codec: container::Codec) -> Self {
+        match codec {
+            container::Codec::Pcm => AwcCodec::Pcm,
+            container::Codec::Adpcm => AwcCodec::Adpcm,
+        }
+    }
+}
+
+impl From<AwcCodec> for container::Codec {
+    fn from(codec: AwcCodec) -> Self {
+        match codec {
+            AwcCodec::Pcm => container::Codec::Pcm,
+            AwcCodec::Adpcm => container::Codec::Adpcm,
+        }
+    }
+}
+
+impl ToString for AwcCodec {
+    fn to_string(&self) -> String {
+        match self {
+            AwcCodec::Pcm => String::from("PCM (Uncompressed)"),
+            AwcCodec::Adpcm => String::from("ADPCM (~4x smaller)"),
+        }
+    }
+}
+
+impl ToString for AwcPackType {
+    fn to_string(&self) -> String {
+        match self {
+            AwcPackType::Simple => String::from("Simple (Not-streamed)"),
+            AwcPackType::Radio => String::from("Radio (Streamed)"),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase", rename = "AudioWaveContainer")]
+pub struct AwcXML {
+    pub version: xml::Value<u8>,
+    pub chunk_indices: xml::Value<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub multi_channel: Option<xml::Value<String>>,
+    #[serde(rename = "Streams")]
+    pub streams: xml::ItemList<AwcStream>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+#[serde(rename = "Item")]
+pub struct AwcStream {
+    name: xml::InlineValue,
+    // The header stream of a multi channel container has no file of its own
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    file_name: Option<xml::InlineValue>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    stream_format: Option<AwcStreamFormat>,
+    #[serde(rename = "Chunks")]
+    chunks: xml::ItemList<AwcChunk>,
+}
+
+impl AwcStream {
+    pub fn name(&self) -> &str {
+        self.name.value()
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_ref().map(|f| f.value())
+    }
+
+    fn format(&self) -> Option<&AwcFormatChunk> {
+        self.chunks.item.iter().find_map(|c| match c {
+            AwcChunk::Format(format) => Some(format),
+            _ => None,
+        })
+    }
+
+    /// Sample count and sample rate declared for the wav file of the stream
+    pub fn declared_format(&self) -> Option<(u64, u32)> {
+        match &self.stream_format {
+            Some(format) => Some((*format.samples.value(), *format.sample_rate.value())),
+            None => self
+                .format()
+                .map(|f| (*f.samples.value(), *f.sample_rate.value())),
+        }
+    }
+
+    pub fn codec(&self) -> Option<AwcCodec> {
+        let codec = match &self.stream_format {
+            Some(stream_format) => &stream_format.codec,
+            None => &self.format()?.codec,
+        };
+        AwcCodec::from_xml_name(codec.value())
+    }
+
+    pub fn format_region(&self) -> Option<FormatRegion> {
+        let format = self.format()?;
+        Some(FormatRegion {
+            loop_point: *format.loop_point.value(),
+            play_begin: (*format.play_begin.value()).clamp(0, u8::MAX.into()) as u8,
+            play_end: *format.play_end.value(),
+            loop_begin: *format.loop_begin.value(),
+            loop_end: *format.loop_end.value(),
+        })
+    }
+
+    pub fn markers(&self) -> Vec<AwcMarker> {
+        self.chunks
+            .item
+            .iter()
+            .find_map(|c| match c {
+                AwcChunk::Markers(markers) => Some(
+                    markers
+                        .markers
+                        .item
+                        .iter()
+                        .filter_map(|m| m.try_into().ok())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Deserializing goes through `AwcRawChunk` as quick-xml can't deserialize the values of
+// internally tagged enums
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "Type", rename_all = "snake_case", try_from = "AwcRawChunk")]
+enum AwcChunk {
+    Peak(AwcPeakChunk),
+    Data,
+    Format(AwcFormatChunk),
+    #[serde(rename = "streamformat")]
+    StreamFormat(AwcStreamFormatChunk),
+    #[serde(rename = "seektable")]
+    SeekTable,
+    Markers(AwcMarkersChunk),
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwcRawChunk {
+    #[serde(rename = "Type")]
+    chunk_type: String,
+    codec: Option<xml::InlineValue>,
+    samples: Option<xml::Value<u64>>,
+    sample_rate: Option<xml::Value<u32>>,
+    headroom: Option<xml::Value<i16>>,
+    play_begin: Option<xml::Value<i16>>,
+    play_end: Option<xml::Value<u16>>,
+    loop_begin: Option<xml::Value<u16>>,
+    loop_end: Option<xml::Value<u16>>,
+    loop_point: Option<xml::Value<i32>>,
+    peak: Option<xml::Unk>,
+    data: Option<xml::InlineValue>,
+    block_size: Option<xml::Value<u32>>,
+    markers: Option<xml::ItemList<AwcMarkerXml>>,
+}
+
+fn required<T>(value: Option<T>, field: &str) -> Result<T, String> {
+    value.ok_or(format!("missing field `{}`", field))
+}
+
+impl TryFrom<AwcRawChunk> for AwcChunk {
+    type Error = String;
+
+    fn try_from(raw: AwcRawChunk) -> Result<Self, Self::Error> {
+        let chunk = match raw.chunk_type.as_str() {
+            "peak" => AwcChunk::Peak(AwcPeakChunk { data: raw.data }),
+            "data" => AwcChunk::Data,
+            "format" => AwcChunk::Format(AwcFormatChunk {
+                codec: required(raw.codec, "Codec")?,
+                samples: required(raw.samples, "Samples")?,
+                sample_rate: required(raw.sample_rate, "SampleRate")?,
+                headroom: raw.headroom.unwrap_or(xml::Value::new(-161)),
+                play_begin: raw.play_begin.unwrap_or(xml::Value::new(0)),
+                play_end: raw.play_end.unwrap_or(xml::Value::new(0)),
+                loop_begin: raw.loop_begin.unwrap_or(xml::Value::new(0)),
+                loop_end: raw.loop_end.unwrap_or(xml::Value::new(0)),
+                loop_point: raw.loop_point.unwrap_or(xml::Value::new(-1)),
+                peak: raw.peak.unwrap_or(xml::Unk::new("0")),
+            }),
+            "streamformat" => AwcChunk::StreamFormat(AwcStreamFormatChunk {
+                block_size: required(raw.block_size, "BlockSize")?,
+            }),
+            "seektable" => AwcChunk::SeekTable,
+            "markers" => AwcChunk::Markers(AwcMarkersChunk {
+                markers: raw.markers.unwrap_or(xml::ItemList { item: vec![] }),
+            }),
+            other => return Err(format!("unsupported chunk type `{}`", other)),
+        };
+        Ok(chunk)
+    }
+}
+
+// Space separated peak of every block, left out for streams without samples
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwcPeakChunk {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    data: Option<xml::InlineValue>,
+}
+
+impl AwcPeakChunk {
+    fn new(peaks: &container::Peaks) -> Self {
+        let data: Vec<String> = peaks.blocks.iter().map(u16::to_string).collect();
+        let data = data.join(" ");
+        Self {
+            data: (!data.is_empty()).then(|| xml::InlineValue::new(&data)),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwcMarkersChunk {
+    markers: xml::ItemList<AwcMarkerXml>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+#[serde(rename = "Item")]
+struct AwcMarkerXml {
+    name: xml::InlineValue,
+    value: xml::Value<u32>,
+    sample_offset: xml::Value<u32>,
+}
+
+impl From<&AwcMarker> for AwcMarkerXml {
+    fn from(marker: &AwcMarker) -> Self {
+        Self {
+            name: xml::InlineValue::new(&Hash::from_value(marker.name).hex()),
+            value: xml::Value::new(marker.value),
+            sample_offset: xml::Value::new(marker.sample_offset),
+        }
+    }
+}
+
+impl TryFrom<&AwcMarkerXml> for AwcMarker {
+    type Error = anyhow::Error;
+
+    fn try_from(marker: &AwcMarkerXml) -> Result<Self> {
+        Ok(Self {
+            name: marker.name.value().parse::<Hash>()?.value(),
+            value: *marker.value.value(),
+            sample_offset: *marker.sample_offset.value(),
+        })
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwcStreamFormatChunk {
+    block_size: xml::Value<u32>,
+}
+
+// Format of a single channel in a multi channel container
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwcStreamFormat {
+    codec: xml::InlineValue,
+    samples: xml::Value<u64>,
+    sample_rate: xml::Value<u32>,
+    headroom: xml::Value<i16>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+#[serde(rename = "Item")]
+struct AwcFormatChunk {
+    // #[serde(rename = "Type")]
+    // chunk_type: xml::InlineValue,
+    codec: xml::InlineValue,
+    samples: xml::Value<u64>,
+    sample_rate: xml::Value<u32>,
+    // Mostly -161
+    headroom: xml::Value<i16>,
+    play_begin: xml::Value<i16>,
+    play_end: xml::Value<u16>,
+    loop_begin: xml::Value<u16>,
+    loop_end: xml::Value<u16>,
+    loop_point: xml::Value<i32>,
+    peak: xml::Unk,
+}
+
+impl AwcFormatChunk {
+    pub fn new(
+        samples: u64,
+        sample_rate: u32,
+        codec: AwcCodec,
+        region: &FormatRegion,
+        peak: u32,
+    ) -> Self {
+        Self {
+            // chunk_type: xml::InlineValue::new("format"),
+            codec: xml::InlineValue::new(codec.xml_name()),
+            samples: xml::Value::new(samples),
+            sample_rate: xml::Value::new(sample_rate),
+            headroom: xml::Value::new(-161),
+            play_begin: xml::Value::new(region.play_begin.into()),
+            play_end: xml::Value::new(region.play_end),
+            loop_begin: xml::Value::new(region.loop_begin),
+            loop_end: xml::Value::new(region.loop_end),
+            loop_point: xml::Value::new(region.loop_point),
+            peak: xml::Unk::new(&peak.to_string()),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+
+use crate::{
+    awc_files::{container, streamed},
+    utils::wav,
+};
+
+use super::awc::{AwcCodec, AwcEntry, AwcMarker, AwcPack, AwcPackType, AwcXML, FormatRegion};
+
+struct ImportedEntry {
+    name: String,
+    codec: AwcCodec,
+    wav: wav::Wav,
+    // Multi channel containers have no format chunks, they always play the whole file
+    region: Option<FormatRegion>,
+    markers: Vec<AwcMarker>,
+}
+
+/// Name of the container described by an awc file, strips the `.awc`, `.xml` and `.awc.xml`
+/// extensions
+pub fn pack_name(path: &Path) -> Result<String> {
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow!("Invalid awc path: {}", path.display()))?
+        .to_string_lossy()
+        .to_string();
+    let mut name = file_name.as_str();
+    for ext in [".xml", ".awc"] {
+        if name.to_lowercase().ends_with(ext) {
+            name = &name[..name.len() - ext.len()];
+        }
+    }
+    Ok(name.to_string())
+}
+
+/// Recreates an awc pack from a binary .awc or CodeWalker .awc.xml file.
+/// The stream data is decoded to wav files in `awc_packs/<pack>/`.
+pub fn import_awc(proj_loc: &Path, path: &Path) -> Result<AwcPack> {
+    let name = pack_name(path)?;
+    let is_xml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+
+    let (pack_type, imported) = if is_xml {
+        read_xml(path, &name)?
+    } else {
+        read_binary(path, &name)?
+    };
+
+    let pack_dir = proj_loc.join("awc_packs").join(&name);
+    fs::create_dir_all(&pack_dir)?;
+
+    let mut entries = vec![];
+    for imported in imported {
+        entries.push(write_entry(proj_loc, &pack_dir, imported)?);
+    }
+
+    Ok(AwcPack {
+        name,
+        pack_type,
+        entries,
+    })
+}
+
+fn write_entry(proj_loc: &Path, pack_dir: &Path, imported: ImportedEntry) -> Result<AwcEntry> {
+    let wav_path = pack_dir.join(format!("{}.wav", imported.name));
+    imported.wav.write(&wav_path)?;
+
+    let mut entry = AwcEntry::new(
+        wav_path.strip_prefix(proj_loc)?.to_path_buf(),
+        &imported.name,
+        imported.codec,
+        imported.wav.sample_rate,
+        imported.wav.sample_count() as u64,
+        imported.wav.channels,
+    );
+    if let Some(region) = &imported.region {
+        entry.set_format_region(region);
+    }
+    entry.markers = imported.markers;
+    Ok(entry)
+}
+
+// Stream names are stored as hashes, so the original names can't be recovered
+fn read_binary(path: &Path, name: &str) -> Result<(AwcPackType, Vec<ImportedEntry>)> {
+    let awc = container::Container::read(path)?;
+
+    if awc.multi_channel {
+        let channels = streamed::read_channels(&awc)?;
+        let first = channels
+            .first()
+            .ok_or(anyhow!("{} has no channels", path.display()))?;
+        let markers = awc
+            .streams
+            .iter()
+            .find(|s| s.id == container::stream_id(&first.name))
+            .map(|s| s.markers().iter().map(AwcMarker::from).collect())
+            .unwrap_or_default();
+        let mono_channels: Vec<wav::Wav> = channels
+            .iter()
+            .map(|c| wav::Wav {
+                channels: 1,
+                sample_rate: c.sample_rate.into(),
+                samples: c.samples.clone(),
+            })
+            .collect();
+
+        let entry = ImportedEntry {
+            name: name.to_string(),
+            codec: first.codec.into(),
+            wav: wav::Wav::interleave(&mono_channels)?,
+            region: None,
+            markers,
+        };
+        return Ok((AwcPackType::Radio, vec![entry]));
+    }
+
+    let mut entries = vec![];
+    for stream in &awc.streams {
+        let Some(format) = stream.format() else {
+            warn!("Skipping stream {:08X} without format chunk", stream.id);
+            continue;
+        };
+        entries.push(ImportedEntry {
+            name: format!("hash_{:08X}", stream.id),
+            codec: format.codec.into(),
+            wav: wav::Wav {
+                channels: 1,
+                sample_rate: format.sample_rate.into(),
+                samples: stream.decode_samples()?,
+            },
+            region: Some(FormatRegion {
+                loop_point: format.loop_point,
+                play_begin: format.play_begin,
+                play_end: format.play_end,
+                loop_begin: format.loop_begin,
+                loop_end: format.loop_end,
+            }),
+            markers: stream.markers().iter().map(AwcMarker::from).collect(),
+        });
+    }
+    Ok((AwcPackType::Simple, entries))
+}
+
+/// Wav file referenced by a stream of the CodeWalker XML of the container `name`
+pub fn find_wav(xml_path: &Path, name: &str, file_name: &str) -> Result<PathBuf> {
+    let xml_dir = xml_path.parent().unwrap_or(Path::new(""));
+    // CodeWalker stores the wav files in a directory named after the container
+    [xml_dir.join(name).join(file_name), xml_dir.join(file_name)]
+        .into_iter()
+        .find(|p| p.exists())
+        .ok_or(anyhow!(
+            "Could not find {} next to {}",
+            file_name,
+            xml_path.display()
+        ))
+}
+
+// Streams of the other channels of an entry, `<name>_right` for `<name>_left` and `<name>_ch1`,
+// `<name>_ch2`, ... for `<name>_ch0`
+fn sibling_streams(name: &str, names: &[&str]) -> Option<(String, Vec<usize>)> {
+    if let Some(base) = name.strip_suffix("_left") {
+        let right = format!("{}_right", base);
+        let j = names.iter().position(|n| *n == right)?;
+        return Some((base.to_string(), vec![j]));
+    }
+
+    let base = name.strip_suffix("_ch0")?;
+    let siblings: Vec<usize> = (1..)
+        .map_while(|c| {
+            let channel = format!("{}_ch{}", base, c);
+            names.iter().position(|n| *n == channel)
+        })
+        .collect();
+    if siblings.is_empty() {
+        return None;
+    }
+    Some((base.to_string(), siblings))
+}
+
+// `<name>_left` / `<name>_right` and `<name>_ch<n>` streams are merged back into a single
+// multi channel entry
+fn read_xml(path: &Path, name: &str) -> Result<(AwcPackType, Vec<ImportedEntry>)> {
+    let awc_xml: AwcXML = quick_xml::de::from_str(&fs::read_to_string(path)?)?;
+    let pack_type = match awc_xml.multi_channel {
+        Some(_) => AwcPackType::Radio,
+        None => AwcPackType::Simple,
+    };
+
+    let mut channels = vec![];
+    for stream in &awc_xml.streams.item {
+        // The header stream of a multi channel container has no file
+        let Some(file_name) = stream.file_name() else {
+            continue;
+        };
+        let wav = wav::Wav::read(&find_wav(path, name, file_name)?)?;
+        channels.push((stream, wav));
+    }
+
+    let mut entries = vec![];
+    let mut used = vec![false; channels.len()];
+    for i in 0..channels.len() {
+        if used[i] {
+            continue;
+        }
+        let (stream, wav) = &channels[i];
+        let names: Vec<&str> = channels.iter().map(|(s, _)| s.name()).collect();
+
+        let (entry_name, wav) = match sibling_streams(stream.name(), &names) {
+            Some((base, siblings)) => {
+                let mut mono_channels = vec![wav.clone()];
+                for j in siblings {
+                    used[j] = true;
+                    mono_channels.push(channels[j].1.clone());
+                }
+                (base, wav::Wav::interleave(&mono_channels)?)
+            }
+            None => (stream.name().to_string(), wav.clone()),
+        };
+        used[i] = true;
+
+        entries.push(ImportedEntry {
+            name: entry_name,
+            codec: stream.codec().unwrap_or_default(),
+            wav,
+            region: stream.format_region(),
+            markers: stream.markers(),
+        });
+    }
+
+    // A multi channel container is a single track
+    if pack_type == AwcPackType::Radio && entries.len() > 1 {
+        let mono_channels: Vec<wav::Wav> = channels.into_iter().map(|(_, wav)| wav).collect();
+        let codec = entries[0].codec;
+        entries = vec![ImportedEntry {
+            name: name.to_string(),
+            codec,
+            wav: wav::Wav::interleave(&mono_channels)?,
+            region: None,
+            markers: vec![],
+        }];
+    }
+
+    Ok((pack_type, entries))
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+
+use crate::utils::fs::write_atomic;
+
+// Amount of previous info.json versions that are kept
+pub const MAX_BACKUPS: usize = 10;
+const BACKUP_DIR: &str = ".audioforge/backups";
+const BACKUP_PREFIX: &str = "info-";
+
+pub fn backup_dir(proj_loc: &Path) -> PathBuf {
+    proj_loc.join(BACKUP_DIR)
+}
+
+/// Copies the current info.json to the backup directory and removes the oldest backups
+pub fn create_backup(proj_loc: &Path) -> Result<()> {
+    let info_path = proj_loc.join("info.json");
+    if !info_path.exists() {
+        return Ok(());
+    }
+
+    let dir = backup_dir(proj_loc);
+    fs::create_dir_all(&dir)?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    // Zero padded so the names sort chronologically
+    fs::copy(
+        &info_path,
+        dir.join(format!("{}{:020}.json", BACKUP_PREFIX, timestamp)),
+    )?;
+
+    for old in list_backups(proj_loc)?.into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Keeps the info.json of a project before it was migrated from `version`. These aren't rolling
+/// backups, they are never removed.
+pub fn create_migration_backup(proj_loc: &Path, version: u32, info: &str) -> Result<PathBuf> {
+    let dir = backup_dir(proj_loc);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("info.v{}.json.bak", version));
+    write_atomic(&path, info.as_bytes())?;
+    Ok(path)
+}
+
+/// Backups of info.json, newest first
+pub fn list_backups(proj_loc: &Path) -> Result<Vec<PathBuf>> {
+    let dir = backup_dir(proj_loc);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with(BACKUP_PREFIX))
+        })
+        .collect();
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Replaces info.json with the backup, the replaced file is kept as `info.json.broken`
+pub fn restore_backup(proj_loc: &Path, backup: &Path) -> Result<()> {
+    let info_path = proj_loc.join("info.json");
+    if info_path.exists() {
+        fs::copy(&info_path, proj_loc.join("info.json.broken"))?;
+    }
+    write_atomic(&info_path, &fs::read(backup)?)
+}
+
+/// Whether opening a project failed because info.json couldn't be parsed, in which case a
+/// backup can be restored
+pub fn is_parse_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<serde_json::Error>().is_some()
+}
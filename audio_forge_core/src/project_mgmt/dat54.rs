@@ -0,0 +1,257 @@
+use crate::{
+    dat_files::{dat54, rel},
+    utils::xml,
+};
+
+use super::awc::{AwcEntry, AwcPack};
+
+// A SimpleSound for a single awc stream
+struct StreamSound<'a> {
+    stream_name: String,
+    container_path: String,
+    header: &'a dat54::Header,
+    looped: bool,
+}
+
+impl StreamSound<'_> {
+    // Looped streams are played through a LoopingSound named after the stream
+    fn simple_sound_name(&self) -> String {
+        match self.looped {
+            true => child_sound_name(&self.stream_name),
+            false => self.stream_name.clone(),
+        }
+    }
+
+    // The LoopingSound already applies the volume of the entry
+    fn simple_sound_header(&self) -> dat54::Header {
+        match self.looped {
+            true => dat54::Header {
+                volume: 0,
+                ..self.header.clone()
+            },
+            false => self.header.clone(),
+        }
+    }
+}
+
+// Name of the SimpleSound played by the LoopingSound of a looped stream
+fn child_sound_name(stream_name: &str) -> String {
+    format!("{}_child", stream_name)
+}
+
+/// Names of the sounds generated for the entry
+pub fn sound_names(entry: &AwcEntry) -> Vec<String> {
+    let mut names = entry.stream_names();
+    if entry.looped {
+        let children: Vec<String> = names.iter().map(|n| child_sound_name(n)).collect();
+        names.extend(children);
+    }
+    names
+}
+
+// Container paths and sounds of every stream in the given packs
+fn stream_sounds<'a>(packs: &[&'a AwcPack]) -> (Vec<String>, Vec<StreamSound<'a>>) {
+    let mut container_paths = vec![];
+    let mut sounds = vec![];
+
+    for pack in packs {
+        for entry in &pack.entries {
+            let container_path = pack.container_path(entry);
+            if !container_paths.contains(&container_path) {
+                container_paths.push(container_path.clone());
+            }
+
+            for stream_name in entry.stream_names() {
+                sounds.push(StreamSound {
+                    stream_name,
+                    container_path: container_path.clone(),
+                    header: &entry.headers,
+                    looped: entry.looped,
+                });
+            }
+        }
+    }
+
+    (container_paths, sounds)
+}
+
+/// Compiled dat54.rel with a SimpleSound for every stream in the given packs, looped streams are
+/// wrapped in a LoopingSound
+pub fn rel_from_awc_packs(packs: &[&AwcPack]) -> rel::RelFile {
+    let (container_paths, sounds) = stream_sounds(packs);
+    let mut items = vec![];
+    for sound in &sounds {
+        items.push(dat54::simple_sound(
+            &sound.simple_sound_name(),
+            &sound.simple_sound_header(),
+            &sound.container_path,
+            &sound.stream_name,
+        ));
+        if sound.looped {
+            items.push(dat54::looping_sound(
+                &sound.stream_name,
+                sound.header,
+                &sound.simple_sound_name(),
+            ));
+        }
+    }
+
+    rel::RelFile {
+        rel_type: dat54::REL_TYPE,
+        version: dat54::VERSION,
+        name_table: container_paths,
+        items,
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename = "Dat54", rename_all = "PascalCase")]
+pub struct Dat54Xml {
+    pub version: xml::Value<u32>,
+    pub container_paths: xml::ItemList<xml::InlineValue>,
+    pub items: xml::ItemList<Dat54Item>,
+}
+
+impl Dat54Xml {
+    /// Creates a SimpleSound for every stream in the given packs, looped streams are wrapped in a
+    /// LoopingSound
+    pub fn from_awc_packs(packs: &[&AwcPack]) -> Self {
+        let (container_paths, sounds) = stream_sounds(packs);
+        let mut items = vec![];
+        for sound in &sounds {
+            items.push(Dat54Item::SimpleSound(Dat54SimpleSound::new(
+                &sound.simple_sound_name(),
+                &sound.stream_name,
+                &sound.container_path,
+                &sound.simple_sound_header(),
+            )));
+            if sound.looped {
+                items.push(Dat54Item::LoopingSound(Dat54LoopingSound::new(
+                    &sound.stream_name,
+                    &sound.simple_sound_name(),
+                    sound.header,
+                )));
+            }
+        }
+
+        Self {
+            version: xml::Value::new(dat54::VERSION),
+            container_paths: xml::ItemList {
+                item: container_paths
+                    .iter()
+                    .map(|p| xml::InlineValue::new(p))
+                    .collect(),
+            },
+            items: xml::ItemList { item: items },
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+pub enum Dat54Item {
+    SimpleSound(Dat54SimpleSound),
+    LoopingSound(Dat54LoopingSound),
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Dat54SimpleSound {
+    #[serde(rename = "@type")]
+    item_type: String,
+    name: xml::InlineValue,
+    header: Dat54Header,
+    container_name: xml::InlineValue,
+    file_name: xml::InlineValue,
+    wave_slot_num: xml::Value<u8>,
+}
+
+impl Dat54SimpleSound {
+    pub fn new(
+        name: &str,
+        stream_name: &str,
+        container_path: &str,
+        header: &dat54::Header,
+    ) -> Self {
+        Self {
+            item_type: "SimpleSound".to_string(),
+            name: xml::InlineValue::new(name),
+            header: Dat54Header::from(header),
+            container_name: xml::InlineValue::new(container_path),
+            file_name: xml::InlineValue::new(stream_name),
+            wave_slot_num: xml::Value::new(0),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Dat54LoopingSound {
+    #[serde(rename = "@type")]
+    item_type: String,
+    name: xml::InlineValue,
+    header: Dat54Header,
+    // -1 loops until the sound is stopped
+    loop_count: xml::Value<i16>,
+    loop_count_variance: xml::Value<i16>,
+    loop_point: xml::Value<i16>,
+    child_sound: xml::InlineValue,
+    loop_count_parameter: xml::InlineValue,
+}
+
+impl Dat54LoopingSound {
+    pub fn new(name: &str, child_sound: &str, header: &dat54::Header) -> Self {
+        Self {
+            item_type: "LoopingSound".to_string(),
+            name: xml::InlineValue::new(name),
+            header: Dat54Header::from(header),
+            loop_count: xml::Value::new(-1),
+            loop_count_variance: xml::Value::new(0),
+            loop_point: xml::Value::new(0),
+            child_sound: xml::InlineValue::new(child_sound),
+            loop_count_parameter: xml::InlineValue::new(""),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Dat54Header {
+    flags: xml::Value<String>,
+    volume: xml::Value<u16>,
+    volume_curve: xml::InlineValue,
+    volume_curve_distance: xml::Value<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doppler_factor: Option<xml::Value<u16>>,
+    category: xml::InlineValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attack_time: Option<xml::Value<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_time: Option<xml::Value<u16>>,
+    unk20: xml::Value<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo_x: Option<xml::Value<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo_y: Option<xml::Value<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo_z: Option<xml::Value<u16>>,
+}
+
+impl From<&dat54::Header> for Dat54Header {
+    fn from(header: &dat54::Header) -> Self {
+        Self {
+            flags: xml::Value::new(format!("0x{:08X}", header.flags())),
+            volume: xml::Value::new(header.volume),
+            volume_curve: xml::InlineValue::new(&header.volume_curve.to_string()),
+            volume_curve_distance: xml::Value::new(header.volume_curve_distance),
+            doppler_factor: header.doppler_factor.map(xml::Value::new),
+            category: xml::InlineValue::new(&header.category.to_string()),
+            attack_time: header.attack_time.map(xml::Value::new),
+            release_time: header.release_time.map(xml::Value::new),
+            unk20: xml::Value::new(header.unk20),
+            echo_x: header.echo_x.map(xml::Value::new),
+            echo_y: header.echo_y.map(xml::Value::new),
+            echo_z: header.echo_z.map(xml::Value::new),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use super::awc::AwcPack;
+
+// Directory of the resource holding a wavepack directory per awc pack
+pub const SFX_DIR: &str = "audio/sfx";
+// Directory of the resource holding the sound data
+pub const DATA_DIR: &str = "data";
+
+/// Name of the sound data of a resource, derived from the project folder name.
+/// The game appends the `54.rel` extension itself.
+pub fn sound_data_name(proj_loc: &Path) -> String {
+    let project_name = proj_loc
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let project_name: String = project_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_sounds.dat", project_name)
+}
+
+/// `fxmanifest.lua` registering every pack as wavepack and the sound data of the resource
+pub fn manifest(packs: &[AwcPack], sound_data_name: &str) -> String {
+    let mut manifest = String::from("fx_version 'cerulean'\ngame 'gta5'\n\nfiles {\n");
+    for pack in packs {
+        manifest.push_str(&format!("    '{}/{}/*.awc',\n", SFX_DIR, pack.name));
+    }
+    manifest.push_str(&format!(
+        "    '{}/{}54.rel',\n    '{}/{}54.nametable',\n}}\n\n",
+        DATA_DIR, sound_data_name, DATA_DIR, sound_data_name
+    ));
+
+    manifest.push_str(&format!(
+        "data_file 'AUDIO_SOUNDDATA' '{}/{}'\n",
+        DATA_DIR, sound_data_name
+    ));
+    for pack in packs {
+        manifest.push_str(&format!(
+            "data_file 'AUDIO_WAVEPACK' '{}/{}'\n",
+            SFX_DIR, pack.name
+        ));
+    }
+    manifest
+}
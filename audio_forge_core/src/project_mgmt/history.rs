@@ -0,0 +1,313 @@
+// Undoable edits of a project.
+//
+// Every change goes through a `Command` holding enough information to apply and revert it.
+// Packs are stored sorted by name, so pack indices stay valid as long as commands are undone in
+// reverse order. Packs are added and removed at a recorded index, names aren't unique while a
+// pack is being renamed or imported.
+
+use crate::dat_files::dat54;
+
+use super::{
+    awc::{AwcCodec, AwcEntry, AwcPack, AwcRegion, ChannelMode},
+    project::Project,
+};
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    AddPack {
+        index: usize,
+        pack: AwcPack,
+    },
+    RemovePack {
+        index: usize,
+        pack: AwcPack,
+    },
+    AddEntry {
+        pack: usize,
+        entry: AwcEntry,
+    },
+    RemoveEntry {
+        pack: usize,
+        index: usize,
+        entry: AwcEntry,
+    },
+    SetHeader {
+        pack: usize,
+        entry: usize,
+        old: dat54::Header,
+        new: dat54::Header,
+    },
+    SetLooped {
+        pack: usize,
+        entry: usize,
+        looped: bool,
+    },
+    SetCodec {
+        pack: usize,
+        entry: usize,
+        old: AwcCodec,
+        new: AwcCodec,
+    },
+    SetChannelMode {
+        pack: usize,
+        entry: usize,
+        old: ChannelMode,
+        new: ChannelMode,
+    },
+    SetRegion {
+        pack: usize,
+        entry: usize,
+        old: AwcRegion,
+        new: AwcRegion,
+    },
+}
+
+impl Command {
+    /// Adds the pack at its sorted position
+    pub fn add_pack(project: &Project, pack: AwcPack) -> Command {
+        let index = project.awc_info.partition_point(|p| p <= &pack);
+        Command::AddPack { index, pack }
+    }
+
+    pub fn remove_pack(project: &Project, index: usize) -> Command {
+        Command::RemovePack {
+            index,
+            pack: project.awc_info[index].clone(),
+        }
+    }
+
+    fn apply(&self, project: &mut Project) {
+        match self {
+            Command::AddPack { index, pack } => project.awc_info.insert(*index, pack.clone()),
+            Command::RemovePack { index, .. } => {
+                project.awc_info.remove(*index);
+            }
+            Command::AddEntry { pack, entry } => {
+                project.awc_info[*pack].entries.push(entry.clone())
+            }
+            Command::RemoveEntry { pack, index, .. } => {
+                project.awc_info[*pack].entries.remove(*index);
+            }
+            Command::SetHeader {
+                pack, entry, new, ..
+            } => project.awc_info[*pack].entries[*entry].headers = new.clone(),
+            Command::SetLooped {
+                pack,
+                entry,
+                looped,
+            } => project.awc_info[*pack].entries[*entry].looped = *looped,
+            Command::SetCodec {
+                pack, entry, new, ..
+            } => project.awc_info[*pack].entries[*entry].codec = *new,
+            Command::SetChannelMode {
+                pack, entry, new, ..
+            } => project.awc_info[*pack].entries[*entry].channel_mode = *new,
+            Command::SetRegion {
+                pack, entry, new, ..
+            } => project.awc_info[*pack].entries[*entry].set_region(*new),
+        }
+    }
+
+    fn revert(&self, project: &mut Project) {
+        match self {
+            Command::AddPack { index, .. } => {
+                project.awc_info.remove(*index);
+            }
+            Command::RemovePack { index, pack } => project.awc_info.insert(*index, pack.clone()),
+            Command::AddEntry { pack, .. } => {
+                project.awc_info[*pack].entries.pop();
+            }
+            Command::RemoveEntry { pack, index, entry } => project.awc_info[*pack]
+                .entries
+                .insert(*index, entry.clone()),
+            Command::SetHeader {
+                pack, entry, old, ..
+            } => project.awc_info[*pack].entries[*entry].headers = old.clone(),
+            Command::SetLooped {
+                pack,
+                entry,
+                looped,
+            } => project.awc_info[*pack].entries[*entry].looped = !*looped,
+            Command::SetCodec {
+                pack, entry, old, ..
+            } => project.awc_info[*pack].entries[*entry].codec = *old,
+            Command::SetChannelMode {
+                pack, entry, old, ..
+            } => project.awc_info[*pack].entries[*entry].channel_mode = *old,
+            Command::SetRegion {
+                pack, entry, old, ..
+            } => project.awc_info[*pack].entries[*entry].set_region(*old),
+        }
+    }
+
+    fn describe(&self, project: &Project) -> String {
+        let entry_name = |pack: usize, entry: usize| {
+            format!(
+                "{}/{}",
+                project.awc_info[pack].name, project.awc_info[pack].entries[entry].name
+            )
+        };
+        match self {
+            Command::AddPack { pack, .. } => format!("Add pack {}", pack.name),
+            Command::RemovePack { pack, .. } => format!("Remove pack {}", pack.name),
+            Command::AddEntry { pack, entry } => {
+                format!("Add {}/{}", project.awc_info[*pack].name, entry.name)
+            }
+            Command::RemoveEntry { pack, entry, .. } => {
+                format!("Remove {}/{}", project.awc_info[*pack].name, entry.name)
+            }
+            Command::SetHeader { pack, entry, .. } => {
+                format!("Edit headers of {}", entry_name(*pack, *entry))
+            }
+            Command::SetLooped {
+                pack,
+                entry,
+                looped,
+            } => match looped {
+                true => format!("Loop {}", entry_name(*pack, *entry)),
+                false => format!("Don't loop {}", entry_name(*pack, *entry)),
+            },
+            Command::SetCodec {
+                pack, entry, new, ..
+            } => format!("Use {} for {}", new.to_string(), entry_name(*pack, *entry)),
+            Command::SetChannelMode {
+                pack, entry, new, ..
+            } => format!(
+                "Use {} channels for {}",
+                new.to_string(),
+                entry_name(*pack, *entry)
+            ),
+            Command::SetRegion { pack, entry, .. } => {
+                format!("Edit play range and loop of {}", entry_name(*pack, *entry))
+            }
+        }
+    }
+
+    // Merges a following edit of the same value into this one
+    fn merge(&mut self, next: &Command) -> bool {
+        match (self, next) {
+            (
+                Command::SetHeader {
+                    pack, entry, new, ..
+                },
+                Command::SetHeader {
+                    pack: next_pack,
+                    entry: next_entry,
+                    new: next_new,
+                    ..
+                },
+            ) if pack == next_pack && entry == next_entry => {
+                *new = next_new.clone();
+                true
+            }
+            (
+                Command::SetRegion {
+                    pack, entry, new, ..
+                },
+                Command::SetRegion {
+                    pack: next_pack,
+                    entry: next_entry,
+                    new: next_new,
+                    ..
+                },
+            ) if pack == next_pack && entry == next_entry => {
+                *new = *next_new;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+struct HistoryItem {
+    command: Command,
+    description: String,
+}
+
+/// Undo and redo stacks of the edits of a project
+#[derive(Default)]
+pub struct History {
+    undo: Vec<HistoryItem>,
+    redo: Vec<HistoryItem>,
+    // Whether the next edit may be merged into the last one
+    coalescing: bool,
+}
+
+impl History {
+    /// Applies the command to the project and makes it undoable
+    pub fn apply(&mut self, project: &mut Project, command: Command) {
+        let description = command.describe(project);
+        command.apply(project);
+        self.redo.clear();
+        self.coalescing = false;
+        self.undo.push(HistoryItem {
+            command,
+            description,
+        });
+    }
+
+    /// Like [`History::apply`], but merges the command into the previous one when both edit the
+    /// same value and [`History::end_coalescing`] wasn't called in between. Used for widgets like
+    /// drag values which change the value every frame while they are being dragged.
+    pub fn apply_coalescing(&mut self, project: &mut Project, command: Command) {
+        if self.coalescing && self.redo.is_empty() {
+            if let Some(last) = self.undo.last_mut() {
+                if last.command.merge(&command) {
+                    command.apply(project);
+                    return;
+                }
+            }
+        }
+        self.apply(project, command);
+        self.coalescing = true;
+    }
+
+    /// Ends the current merged edit, the next edit gets its own undo step
+    pub fn end_coalescing(&mut self) {
+        self.coalescing = false;
+    }
+
+    pub fn undo(&mut self, project: &mut Project) -> bool {
+        let Some(item) = self.undo.pop() else {
+            return false;
+        };
+        item.command.revert(project);
+        self.coalescing = false;
+        self.redo.push(item);
+        true
+    }
+
+    pub fn redo(&mut self, project: &mut Project) -> bool {
+        let Some(item) = self.redo.pop() else {
+            return false;
+        };
+        item.command.apply(project);
+        self.coalescing = false;
+        self.undo.push(item);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Descriptions of the undoable edits, oldest first
+    pub fn undo_descriptions(&self) -> impl Iterator<Item = &str> {
+        self.undo.iter().map(|i| i.description.as_str())
+    }
+
+    /// Descriptions of the redoable edits, next redo first
+    pub fn redo_descriptions(&self) -> impl Iterator<Item = &str> {
+        self.redo.iter().rev().map(|i| i.description.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.coalescing = false;
+    }
+}
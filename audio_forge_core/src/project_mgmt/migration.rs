@@ -0,0 +1,241 @@
+// Upgrades of info.json written by older versions of AudioForge.
+//
+// Migrations work on the raw json so they don't depend on the current shape of the project
+// structs. Every migration upgrades the file by exactly one version.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Version of the info.json layout written by this version of AudioForge
+pub const FORMAT_VERSION: u32 = 3;
+
+type Migration = fn(&mut Value) -> Result<()>;
+
+// MIGRATIONS[n] upgrades version n to n + 1
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// Format version of a project file, files written before versioning are version 0
+pub fn format_version(json: &Value) -> Result<u32> {
+    match json.get("format_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or(anyhow!("Invalid format_version: {}", version)),
+    }
+}
+
+/// Upgrades the project file to [`FORMAT_VERSION`]
+pub fn migrate(json: &mut Value) -> Result<()> {
+    let version = format_version(json)?;
+    if version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "The project was written by a newer version of AudioForge (format version {}), \
+             this version supports up to format version {}",
+            version,
+            FORMAT_VERSION
+        ));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(json).map_err(|err| {
+            anyhow!(
+                "Failed to migrate project from format version {}: {}",
+                from,
+                err
+            )
+        })?;
+        json["format_version"] = Value::from(from as u32 + 1);
+    }
+    Ok(())
+}
+
+fn object(value: &mut Value) -> Result<&mut serde_json::Map<String, Value>> {
+    if !value.is_object() {
+        return Err(anyhow!("expected an object, got {}", value));
+    }
+    Ok(value.as_object_mut().unwrap())
+}
+
+// Entries got a codec, older projects declared every stream as ADPCM.
+// Header hashes were stored as bare hex strings.
+fn v0_to_v1(json: &mut Value) -> Result<()> {
+    let Some(packs) = object(json)?
+        .get_mut("awc_info")
+        .and_then(|p| p.as_array_mut())
+    else {
+        return Ok(());
+    };
+
+    for pack in packs {
+        let pack = object(pack)?;
+        let Some(entries) = pack.get_mut("entries").and_then(|e| e.as_array_mut()) else {
+            continue;
+        };
+
+        for entry in entries {
+            let entry = object(entry)?;
+            entry.entry("codec").or_insert_with(|| Value::from("Adpcm"));
+
+            let Some(headers) = entry.get_mut("headers") else {
+                continue;
+            };
+            let headers = object(headers)?;
+            for field in ["category", "volume_curve"] {
+                if let Some(Value::String(hash)) = headers.get_mut(field) {
+                    if !hash.starts_with("hash_") {
+                        *hash = format!("hash_{}", hash);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Sample counts were estimated from the container duration and could be negative. They can only
+// be fixed by counting the wav files, which the project does after migrating.
+fn v1_to_v2(json: &mut Value) -> Result<()> {
+    let Some(packs) = object(json)?
+        .get_mut("awc_info")
+        .and_then(|p| p.as_array_mut())
+    else {
+        return Ok(());
+    };
+
+    for pack in packs {
+        let Some(entries) = object(pack)?
+            .get_mut("entries")
+            .and_then(|e| e.as_array_mut())
+        else {
+            continue;
+        };
+        for entry in entries {
+            let entry = object(entry)?;
+            if entry.get("samples").and_then(|s| s.as_u64()).is_none() {
+                entry.insert(String::from("samples"), Value::from(0));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Entries got a channel layout and mode, older versions always split the source into a left and
+// right stream. The real layout is probed from the wav files by the project after migrating.
+fn v2_to_v3(json: &mut Value) -> Result<()> {
+    let Some(packs) = object(json)?
+        .get_mut("awc_info")
+        .and_then(|p| p.as_array_mut())
+    else {
+        return Ok(());
+    };
+
+    for pack in packs {
+        let Some(entries) = object(pack)?
+            .get_mut("entries")
+            .and_then(|e| e.as_array_mut())
+        else {
+            continue;
+        };
+        for entry in entries {
+            let entry = object(entry)?;
+            entry.entry("channels").or_insert_with(|| Value::from(2));
+            entry
+                .entry("channel_mode")
+                .or_insert_with(|| Value::from("SplitStereo"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::project_mgmt::awc::{AwcCodec, AwcPack, ChannelMode};
+    use crate::utils::hash::Hash;
+
+    fn v0_entry(name: &str) -> Value {
+        json!({
+            "path": format!("awc_packs/n10/{}.wav", name),
+            "name": name,
+            "looped": false,
+            "headers": {
+                "volume": 100,
+                "volume_curve": "C2770146",
+                "volume_curve_distance": 5,
+                "doppler_factor": null,
+                "category": "02C7B342",
+                "attack_time": null,
+                "release_time": null,
+                "unk20": 0,
+                "echo_x": null,
+                "echo_y": null,
+                "echo_z": null
+            },
+            "sample_rate": 32000,
+            "samples": -44100
+        })
+    }
+
+    fn packs(json: &Value) -> Vec<AwcPack> {
+        serde_json::from_value(json["awc_info"].clone()).unwrap()
+    }
+
+    #[test]
+    fn v0_project_is_upgraded() {
+        let mut pcm = v0_entry("six");
+        pcm["codec"] = Value::from("Pcm");
+        let mut json = json!({
+            "awc_info": [{
+                "name": "n10",
+                "pack_type": "Simple",
+                "entries": [v0_entry("boom"), pcm]
+            }]
+        });
+
+        migrate(&mut json).unwrap();
+
+        assert_eq!(format_version(&json).unwrap(), FORMAT_VERSION);
+        let entries = &packs(&json)[0].entries;
+        // Entries without a codec were built as ADPCM
+        assert_eq!(entries[0].codec, AwcCodec::Adpcm);
+        assert_eq!(entries[1].codec, AwcCodec::Pcm);
+        for entry in entries {
+            assert_eq!(entry.channel_mode, ChannelMode::SplitStereo);
+            assert_eq!(entry.headers.category, Hash::from_value(0x02C7B342));
+            assert_eq!(entry.headers.volume_curve, Hash::from_value(0xC2770146));
+        }
+        let entry = &json["awc_info"][0]["entries"][0];
+        assert_eq!(entry["samples"], 0);
+        assert_eq!(entry["channels"], 2);
+    }
+
+    #[test]
+    fn v2_project_keeps_its_values() {
+        let mut entry = v0_entry("boom");
+        entry["codec"] = Value::from("Pcm");
+        entry["samples"] = Value::from(1000);
+        entry["headers"]["category"] = Value::from("hash_02C7B342");
+        entry["headers"]["volume_curve"] = Value::from("hash_C2770146");
+        let mut json = json!({
+            "format_version": 2,
+            "awc_info": [{ "name": "n10", "pack_type": "Simple", "entries": [entry] }]
+        });
+
+        migrate(&mut json).unwrap();
+
+        assert_eq!(json["format_version"], 3);
+        let entry = &packs(&json)[0].entries[0];
+        assert_eq!(entry.codec, AwcCodec::Pcm);
+        assert_eq!(entry.channel_mode, ChannelMode::SplitStereo);
+        assert_eq!(json["awc_info"][0]["entries"][0]["samples"], 1000);
+    }
+
+    #[test]
+    fn newer_projects_are_rejected() {
+        let mut json = json!({ "format_version": FORMAT_VERSION + 1, "awc_info": [] });
+        assert!(migrate(&mut json).is_err());
+    }
+}
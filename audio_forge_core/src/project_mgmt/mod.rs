@@ -0,0 +1,10 @@
+pub mod awc;
+pub mod awc_import;
+pub mod backup;
+pub mod dat54;
+pub mod fivem;
+pub mod history;
+pub mod migration;
+pub mod project;
+pub mod validation;
+pub mod verification;
@@ -0,0 +1,434 @@
+use log::{info, warn};
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    awc_files::container,
+    dat_files::catalogue::Catalogue,
+    jobs::JobContext,
+    utils::{fs::write_atomic, transcoder, wav::WavCues, xml},
+};
+
+use super::{
+    awc::{self, AwcEntry, AwcPack, AwcXML},
+    awc_import, backup,
+    dat54::{self, Dat54Xml},
+    fivem, migration, verification,
+};
+use anyhow::{anyhow, Result};
+
+/// An entry whose sample count didn't match its wav file
+#[derive(Debug, Clone)]
+pub struct SampleCountFix {
+    pub pack: String,
+    pub entry: String,
+    pub old: u64,
+    pub new: u64,
+}
+
+/// Settings for importing audio files
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportOptions {
+    /// Don't take the loop region and markers from the smpl and cue chunks of wav files
+    pub ignore_cues: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Project {
+    format_version: u32,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub location: PathBuf,
+    pub awc_info: Vec<awc::AwcPack>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub catalogue: Catalogue,
+}
+
+impl Project {
+    pub fn create_project(path: &Path) -> Result<()> {
+        let proj = Project {
+            format_version: migration::FORMAT_VERSION,
+            location: path.to_path_buf().clone(),
+            awc_info: vec![],
+            catalogue: Catalogue::default(),
+        };
+        proj.save()?;
+        Ok(())
+    }
+
+    pub fn open_project(path: &Path) -> Result<Project> {
+        let mut f = File::open(path.join("info.json"))?;
+        let mut buffer = String::new();
+
+        f.read_to_string(&mut buffer)?;
+        let mut json: serde_json::Value = serde_json::from_str(&buffer)?;
+        let version = migration::format_version(&json)?;
+        migration::migrate(&mut json)?;
+
+        let mut proj: Project = serde_json::from_value(json)?;
+        proj.location = path.to_path_buf().clone();
+        proj.catalogue = Catalogue::load(path).unwrap_or_else(|err| {
+            warn!(
+                "Using the builtin catalogue, failed to load the project catalogue: {:#}",
+                err
+            );
+            Catalogue::default()
+        });
+
+        if version < migration::FORMAT_VERSION {
+            // Keep the original around in case the migration lost something
+            let backup = backup::create_migration_backup(path, version, &buffer)?;
+            // Older versions stored estimated sample counts
+            if version < 2 {
+                match proj.rescan_samples(&JobContext::default()) {
+                    Ok(fixes) => proj.apply_sample_counts(&fixes),
+                    Err(err) => warn!(
+                        "Failed to recount the samples, run the rescan command once the files are back: {:#}",
+                        err
+                    ),
+                }
+            }
+            // Older versions didn't record the channel layout of the sources
+            if version < 3 {
+                if let Err(err) = proj.probe_channel_layouts() {
+                    warn!(
+                        "Failed to read the channel layouts, the remaining entries are kept as stereo: {:#}",
+                        err
+                    );
+                }
+            }
+            proj.save()?;
+            info!(
+                "Migrated project from format version {} to {}, the original is kept in {}",
+                version,
+                migration::FORMAT_VERSION,
+                backup.display()
+            );
+        }
+
+        Ok(proj)
+    }
+
+    pub fn is_folder_a_project(path: &Path) -> bool {
+        path.join("info.json").exists()
+    }
+
+    pub fn get_mut_entries_slice(&mut self) -> &mut [AwcPack] {
+        return self.awc_info.as_mut_slice();
+    }
+
+    /// Writes info.json atomically, the previous version is moved to the backups
+    pub fn save(&self) -> Result<()> {
+        let json_str = serde_json::to_string(self)?;
+        let info_path = self.location.join("info.json");
+        // Nothing changed, don't push out older backups with copies of the same file
+        if fs::read(&info_path).is_ok_and(|current| current == json_str.as_bytes()) {
+            return Ok(());
+        }
+
+        backup::create_backup(&self.location)?;
+        write_atomic(&info_path, json_str.as_bytes())?;
+        info!("Saved project");
+        Ok(())
+    }
+
+    /// Counts the samples of every entry from its wav file and returns the entries whose stored
+    /// count is wrong
+    pub fn rescan_samples(&self, job: &JobContext) -> Result<Vec<SampleCountFix>> {
+        let entries: Vec<(&AwcPack, &AwcEntry)> = self
+            .awc_info
+            .iter()
+            .flat_map(|pack| pack.entries.iter().map(move |entry| (pack, entry)))
+            .collect();
+
+        let mut fixes = vec![];
+        for (i, (pack, entry)) in entries.iter().enumerate() {
+            job.check_cancelled()?;
+            let samples = entry.count_samples(&self.location, &job.part(i, entries.len()))?;
+            if samples != entry.samples() {
+                fixes.push(SampleCountFix {
+                    pack: pack.name.clone(),
+                    entry: entry.name.clone(),
+                    old: entry.samples(),
+                    new: samples,
+                });
+            }
+        }
+        Ok(fixes)
+    }
+
+    /// Reads the channel count of every entry from its wav file and resets its channel mode to
+    /// the one matching the layout
+    pub fn probe_channel_layouts(&mut self) -> Result<()> {
+        for pack in &mut self.awc_info {
+            for entry in &mut pack.entries {
+                let info = transcoder::probe(&entry.source_path(&self.location))?;
+                entry.set_channels(info.channels);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the counts of [`Project::rescan_samples`], entries removed in the meantime are
+    /// skipped
+    pub fn apply_sample_counts(&mut self, fixes: &[SampleCountFix]) {
+        for fix in fixes {
+            let entry = self
+                .awc_info
+                .iter_mut()
+                .filter(|p| p.name == fix.pack)
+                .flat_map(|p| p.entries.iter_mut())
+                .find(|e| e.name == fix.entry);
+            if let Some(entry) = entry {
+                entry.set_samples(fix.new);
+                info!(
+                    "Fixed sample count of {}/{}: {} -> {}",
+                    fix.pack, fix.entry, fix.old, fix.new
+                );
+            }
+        }
+    }
+
+    pub fn add_awc_pack(&mut self, pack: AwcPack) {
+        self.awc_info.push(pack);
+        self.awc_info.sort();
+    }
+
+    /// Transcodes an audio file to a wav in the pack directory and creates an entry for it.
+    /// The entry still has to be added to the pack.
+    pub fn import_audio_file(
+        &self,
+        awc_pack_index: usize,
+        path: &Path,
+        options: ImportOptions,
+        job: &JobContext,
+    ) -> Result<AwcEntry> {
+        transcoder::validate_audio_file(path)?;
+        let awc_pack = &self.awc_info[awc_pack_index];
+
+        let entry_name = &path
+            .file_stem()
+            .ok_or(anyhow!("Invalid audio file path: {}", path.display()))?
+            .to_string_lossy()
+            .to_string();
+        // The wav would replace the one of the existing entry
+        if awc_pack.entries.iter().any(|e| &e.name == entry_name) {
+            return Err(anyhow!(
+                "{} already has an entry named {}",
+                awc_pack.name,
+                entry_name
+            ));
+        }
+        let output_dir = self.location.join("awc_packs").join(&awc_pack.name);
+        fs::create_dir_all(output_dir.as_path())?;
+
+        let output = output_dir.join(format!("{}.wav", entry_name));
+        transcoder::encode_to_wav(path, &output, job)?;
+
+        let mut entry = match AwcEntry::from_file(
+            &self.location,
+            &output_dir,
+            entry_name,
+            awc_pack.pack_type.default_codec(),
+            job,
+        ) {
+            Ok(entry) => entry,
+            Err(err) => {
+                let _ = fs::remove_file(&output);
+                return Err(err);
+            }
+        };
+
+        // The transcoded wav doesn't keep the cue chunks, they are read from the source file
+        if !options.ignore_cues {
+            match WavCues::read(path) {
+                Ok(cues) => entry.apply_wav_cues(&cues),
+                Err(err) => warn!("Ignoring the cue points of {}: {:#}", path.display(), err),
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Recreates a pack from an awc file, the pack still has to be added to the project
+    pub fn import_awc_pack(&self, path: &Path) -> Result<AwcPack> {
+        // Checked before importing, the wavs would replace the ones of the existing pack
+        let name = awc_import::pack_name(path)?;
+        if self.awc_info.iter().any(|p| p.name == name) {
+            return Err(anyhow!("An awc pack named {} already exists", name));
+        }
+        let pack = awc_import::import_awc(&self.location, path)?;
+        info!(
+            "Imported {} entries from {}",
+            pack.entries.len(),
+            path.display()
+        );
+        Ok(pack)
+    }
+
+    // Splits the entries of the pack into mono channels in its build directory
+    fn build_pack_channels(&self, awc_pack: &AwcPack, job: &JobContext) -> Result<PathBuf> {
+        let build_dir = awc_pack.build_dir(&self.location);
+        fs::create_dir_all(&build_dir)?;
+
+        let count = awc_pack.entries.len();
+        for (i, ele) in awc_pack.entries.iter().enumerate() {
+            ele.generate_splitted_variant(&self.location, &build_dir, &job.part(i, count))?;
+        }
+        Ok(build_dir)
+    }
+
+    pub fn generate_awc_file(
+        &self,
+        awc_pack_index: usize,
+        output_format: awc::AwcOutputFormat,
+        job: &JobContext,
+    ) -> Result<()> {
+        let awc_pack = &self.awc_info[awc_pack_index];
+        let build_dir = self.build_pack_channels(awc_pack, job)?;
+        job.check_cancelled()?;
+
+        match output_format {
+            awc::AwcOutputFormat::Binary => {
+                self.write_awc_binary(awc_pack, &build_dir, &self.location.join("output/awc/"))
+            }
+            awc::AwcOutputFormat::Xml => self.write_awc_xml(awc_pack, &build_dir),
+        }
+    }
+
+    /// Assembles a FiveM resource in `output/awc_resource` containing every pack of the project
+    /// and the sound data referencing them
+    pub fn generate_fivem_resource(&self, job: &JobContext) -> Result<PathBuf> {
+        if self.awc_info.is_empty() {
+            return Err(anyhow!("The project has no awc packs"));
+        }
+
+        let resource_dir = self.location.join("output/awc_resource");
+        // Start from scratch so removed packs don't linger around
+        if resource_dir.exists() {
+            fs::remove_dir_all(&resource_dir)?;
+        }
+        let sfx_dir = resource_dir.join(fivem::SFX_DIR);
+        let data_dir = resource_dir.join(fivem::DATA_DIR);
+        fs::create_dir_all(&sfx_dir)?;
+        fs::create_dir_all(&data_dir)?;
+
+        let count = self.awc_info.len();
+        for (i, awc_pack) in self.awc_info.iter().enumerate() {
+            let build_dir = self.build_pack_channels(awc_pack, &job.part(i, count))?;
+            job.check_cancelled()?;
+            self.write_awc_binary(awc_pack, &build_dir, &sfx_dir)?;
+        }
+
+        let sound_data_name = fivem::sound_data_name(&self.location);
+        let packs: Vec<&AwcPack> = self.awc_info.iter().collect();
+        dat54::rel_from_awc_packs(&packs)
+            .write(&data_dir.join(format!("{}54.rel", sound_data_name)))?;
+
+        let mut f = File::create(resource_dir.join("fxmanifest.lua"))?;
+        f.write_all(fivem::manifest(&self.awc_info, &sound_data_name).as_bytes())?;
+        info!("Generated FiveM resource in {}", resource_dir.display());
+
+        Ok(resource_dir)
+    }
+
+    pub fn generate_dat54_file(
+        &self,
+        awc_pack_index: usize,
+        output_format: awc::AwcOutputFormat,
+    ) -> Result<()> {
+        let awc_pack = &self.awc_info[awc_pack_index];
+        let output_dir = self.location.join("output/dat54/");
+        fs::create_dir_all(&output_dir)?;
+
+        match output_format {
+            awc::AwcOutputFormat::Binary => dat54::rel_from_awc_packs(&[awc_pack])
+                .write(&output_dir.join(format!("{}.dat54.rel", &awc_pack.name)))?,
+            awc::AwcOutputFormat::Xml => write_xml(
+                &Dat54Xml::from_awc_packs(&[awc_pack]),
+                &output_dir.join(format!("{}.dat54.rel.xml", &awc_pack.name)),
+            )?,
+        }
+        info!("Generated dat54 for {}", &awc_pack.name);
+
+        Ok(())
+    }
+
+    // Writes every container of the pack to `<output_dir>/<container path>.awc`
+    fn write_awc_binary(
+        &self,
+        awc_pack: &AwcPack,
+        build_dir: &Path,
+        output_dir: &Path,
+    ) -> Result<()> {
+        let pack_dir = output_dir.join(&awc_pack.name);
+        fs::create_dir_all(&pack_dir)?;
+
+        match awc_pack.pack_type {
+            awc::AwcPackType::Simple => {
+                let mut streams = vec![];
+
+                for ele in &awc_pack.entries {
+                    streams.extend(ele.to_binary_streams(build_dir)?);
+                }
+
+                container::Container::new(streams)
+                    .write(&pack_dir.join(format!("{}.awc", &awc_pack.name)))?;
+            }
+            awc::AwcPackType::Radio => {
+                // Every track of a radio pack is its own streamed container
+                for ele in &awc_pack.entries {
+                    ele.to_streamed_container(build_dir)?
+                        .write(&pack_dir.join(format!("{}.awc", &ele.name)))?;
+                }
+            }
+        };
+        info!("Generated awc files for {}", &awc_pack.name);
+
+        Ok(())
+    }
+
+    fn write_awc_xml(&self, awc_pack: &AwcPack, build_dir: &Path) -> Result<()> {
+        match awc_pack.pack_type {
+            awc::AwcPackType::Simple => {
+                let mut streams = vec![];
+
+                for ele in &awc_pack.entries {
+                    let entry_streams = ele.to_xml_stream(build_dir)?;
+                    streams.extend(entry_streams);
+                }
+
+                let awc_xml = AwcXML {
+                    version: xml::Value::new(1),
+                    chunk_indices: xml::Value::new("True".to_string()),
+                    multi_channel: None,
+                    streams: xml::ItemList { item: streams },
+                };
+
+                let xml_path = self
+                    .location
+                    .join(format!("output/awc/.packs/{}.xml", &awc_pack.name));
+                write_xml(&awc_xml, &xml_path)?;
+                verification::verify_awc_xml(&xml_path)?;
+            }
+            awc::AwcPackType::Radio => {
+                for ele in &awc_pack.entries {
+                    let xml_path = build_dir.join(format!("{}.xml", &ele.name));
+                    write_xml(&ele.to_streamed_xml(build_dir)?, &xml_path)?;
+                    verification::verify_awc_xml(&xml_path)?;
+                }
+            }
+        };
+
+        Ok(())
+    }
+}
+
+fn write_xml<T: serde::Serialize>(xml_struct: &T, path: &Path) -> Result<()> {
+    let serialized = xml::serialize_str(xml_struct)?;
+
+    let mut f = File::create(path)?;
+    f.write_all(serialized.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use crate::utils::hash::joaat;
+
+use super::{awc::AwcPackType, dat54, project::Project};
+
+/// Problems that would make the generated files unusable, an empty list means the project can
+/// be built
+pub fn validate_project(project: &Project) -> Vec<String> {
+    let mut problems = vec![];
+    // Sound names of all packs end up in a single dat54 when building a resource
+    let mut sound_names: HashMap<u32, String> = HashMap::new();
+
+    for (i, pack) in project.awc_info.iter().enumerate() {
+        if pack.name.is_empty() {
+            problems.push(format!("Pack #{} has no name", i));
+        }
+        if project.awc_info[..i].iter().any(|p| p.name == pack.name) {
+            problems.push(format!("Pack name {} is used more than once", pack.name));
+        }
+        if pack.entries.is_empty() {
+            problems.push(format!("Pack {} has no entries", pack.name));
+        }
+
+        for (j, entry) in pack.entries.iter().enumerate() {
+            if pack.entries[..j].iter().any(|e| e.name == entry.name) {
+                problems.push(format!(
+                    "Entry name {} is used more than once in pack {}",
+                    entry.name, pack.name
+                ));
+            }
+            let source = entry.source_path(&project.location);
+            if !source.exists() {
+                problems.push(format!(
+                    "Source file of {}/{} is missing: {}",
+                    pack.name,
+                    entry.name,
+                    source.display()
+                ));
+            }
+
+            let region = match pack.pack_type {
+                AwcPackType::Simple => entry.format_region().map(|_| ()),
+                AwcPackType::Radio => entry.check_whole_play_range(),
+            };
+            if let Err(err) = region {
+                problems.push(format!("{}/{}", pack.name, err));
+            }
+
+            for sound_name in dat54::sound_names(entry) {
+                let hash = joaat(&sound_name);
+                match sound_names.get(&hash) {
+                    Some(other) if other == &sound_name => {
+                        problems.push(format!("Sound {} is defined more than once", sound_name))
+                    }
+                    Some(other) => problems.push(format!(
+                        "Sounds {} and {} have the same hash {:08X}",
+                        other, sound_name, hash
+                    )),
+                    None => {
+                        sound_names.insert(hash, sound_name);
+                    }
+                }
+            }
+        }
+    }
+
+    problems
+}
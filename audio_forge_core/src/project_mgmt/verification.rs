@@ -0,0 +1,112 @@
+// Checks of the generated CodeWalker XML against the wav files it references.
+//
+// CodeWalker only reports a missing or mismatching wav file when the XML is imported, which is
+// long after the build, so every build verifies its own output.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::utils::wav::WavInfo;
+
+use super::{awc::AwcXML, awc_import};
+
+/// Generated XML whose streams don't match their wav files
+#[derive(Debug)]
+pub struct VerificationError {
+    pub xml_path: PathBuf,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} doesn't match the generated wav files:",
+            self.xml_path.display()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Checks that every `FileName` of the awc XML exists and is a mono 16-bit wav file with the
+/// declared sample rate and sample count
+pub fn verify_awc_xml(xml_path: &Path) -> Result<()> {
+    let problems = awc_xml_problems(xml_path)?;
+    if !problems.is_empty() {
+        return Err(VerificationError {
+            xml_path: xml_path.to_path_buf(),
+            problems,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Mismatches between the awc XML and its wav files, fails when the XML itself can't be read
+pub fn awc_xml_problems(xml_path: &Path) -> Result<Vec<String>> {
+    let awc_xml: AwcXML = quick_xml::de::from_str(&fs::read_to_string(xml_path)?)?;
+    let container_name = awc_import::pack_name(xml_path)?;
+    let mut problems = vec![];
+
+    for stream in &awc_xml.streams.item {
+        // The header stream of a multi channel container has no file
+        let Some(file_name) = stream.file_name() else {
+            continue;
+        };
+        let name = stream.name();
+
+        let wav_path = match awc_import::find_wav(xml_path, &container_name, file_name) {
+            Ok(path) => path,
+            Err(err) => {
+                problems.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+        let info = match WavInfo::read(&wav_path) {
+            Ok(info) => info,
+            Err(err) => {
+                problems.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+
+        let file = wav_path.display();
+        if info.channels != 1 {
+            problems.push(format!(
+                "{}: {} has {} channels, streams need mono files",
+                name, file, info.channels
+            ));
+        }
+        if !info.is_pcm16() {
+            problems.push(format!("{}: {} isn't a 16-bit PCM wav file", name, file));
+        }
+
+        let Some((samples, sample_rate)) = stream.declared_format() else {
+            problems.push(format!("{}: the XML declares no format", name));
+            continue;
+        };
+        if info.sample_rate != sample_rate {
+            problems.push(format!(
+                "{}: {} has a sample rate of {} Hz, the XML declares {} Hz",
+                name, file, info.sample_rate, sample_rate
+            ));
+        }
+        if info.sample_count != samples {
+            problems.push(format!(
+                "{}: {} has {} samples, the XML declares {}",
+                name, file, info.sample_count, samples
+            ));
+        }
+    }
+
+    Ok(problems)
+}
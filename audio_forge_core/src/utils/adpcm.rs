@@ -0,0 +1,191 @@
+// IMA ADPCM as used in the data chunks of awc files.
+//
+// The data is split in blocks of 2048 bytes. Every block starts with a 4 byte header holding
+// the step index (u8 + 1 byte padding) and the predictor (i16) at the start of the block,
+// followed by 2044 bytes of 4-bit samples (low nibble first). The last block can be shorter.
+
+pub const BLOCK_SIZE: usize = 2048;
+const BLOCK_HEADER_SIZE: usize = 4;
+pub const SAMPLES_PER_BLOCK: usize = (BLOCK_SIZE - BLOCK_HEADER_SIZE) * 2;
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+#[derive(Default)]
+struct State {
+    predictor: i32,
+    step_index: i32,
+}
+
+impl State {
+    fn decode_nibble(&mut self, nibble: u8) -> i16 {
+        let step = STEP_TABLE[self.step_index as usize];
+        let mut diff = step >> 3;
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 8 != 0 {
+            self.predictor -= diff;
+        } else {
+            self.predictor += diff;
+        }
+        self.predictor = self.predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + INDEX_TABLE[nibble as usize]).clamp(0, 88);
+        self.predictor as i16
+    }
+
+    fn encode_sample(&mut self, sample: i16) -> u8 {
+        let mut step = STEP_TABLE[self.step_index as usize];
+        let mut diff = sample as i32 - self.predictor;
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+        for bit in [4, 2, 1] {
+            if diff >= step {
+                nibble |= bit;
+                diff -= step;
+            }
+            step >>= 1;
+        }
+        // Run the decoder so our state stays in sync with whoever decodes the stream
+        self.decode_nibble(nibble);
+        nibble
+    }
+
+    fn write_header(&self, buf: &mut Vec<u8>) {
+        buf.push(self.step_index as u8);
+        buf.push(0);
+        buf.extend_from_slice(&(self.predictor as i16).to_le_bytes());
+    }
+
+    fn read_header(&mut self, header: &[u8]) {
+        self.step_index = (header[0] as i32).clamp(0, 88);
+        self.predictor = i16::from_le_bytes([header[2], header[3]]) as i32;
+    }
+}
+
+/// Size in bytes of the encoded data for the given amount of mono samples
+pub fn encoded_size(sample_count: usize) -> usize {
+    let full_blocks = sample_count / SAMPLES_PER_BLOCK;
+    let remaining = sample_count % SAMPLES_PER_BLOCK;
+    let mut size = full_blocks * BLOCK_SIZE;
+    if remaining > 0 {
+        size += BLOCK_HEADER_SIZE + remaining.div_ceil(2);
+    }
+    size
+}
+
+/// Encodes mono 16-bit PCM samples
+pub fn encode(samples: &[i16]) -> Vec<u8> {
+    let mut state = State::default();
+    let mut buf = Vec::with_capacity(encoded_size(samples.len()));
+
+    for block in samples.chunks(SAMPLES_PER_BLOCK) {
+        state.write_header(&mut buf);
+        for pair in block.chunks(2) {
+            let low = state.encode_sample(pair[0]);
+            let high = pair.get(1).map_or(0, |s| state.encode_sample(*s));
+            buf.push(low | high << 4);
+        }
+    }
+
+    buf
+}
+
+/// Decodes `sample_count` mono 16-bit PCM samples
+pub fn decode(data: &[u8], sample_count: usize) -> Vec<i16> {
+    let mut state = State::default();
+    let mut samples = Vec::with_capacity(sample_count);
+
+    for block in data.chunks(BLOCK_SIZE) {
+        if block.len() < BLOCK_HEADER_SIZE {
+            break;
+        }
+        state.read_header(block);
+        for byte in &block[BLOCK_HEADER_SIZE..] {
+            for nibble in [byte & 0x0F, byte >> 4] {
+                if samples.len() == sample_count {
+                    return samples;
+                }
+                samples.push(state.decode_nibble(nibble));
+            }
+        }
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sine sweep spanning several blocks, the last one partially filled
+    fn signal() -> Vec<i16> {
+        (0..SAMPLES_PER_BLOCK * 3 + 123)
+            .map(|i| {
+                let t = i as f64 / 32000.0;
+                (12000.0 * (2.0 * std::f64::consts::PI * (200.0 + 400.0 * t) * t).sin()) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn block_layout() {
+        let samples = signal();
+        let encoded = encode(&samples);
+        assert_eq!(encoded.len(), encoded_size(samples.len()));
+
+        let blocks: Vec<&[u8]> = encoded.chunks(BLOCK_SIZE).collect();
+        assert_eq!(blocks.len(), samples.len().div_ceil(SAMPLES_PER_BLOCK));
+        for block in &blocks[..blocks.len() - 1] {
+            assert_eq!(block.len(), BLOCK_SIZE);
+        }
+        // Every block header holds a valid step index and its padding byte
+        for block in blocks {
+            assert!(block[0] <= 88);
+            assert_eq!(block[1], 0);
+        }
+    }
+
+    #[test]
+    fn decoded_signal_stays_close() {
+        let samples = signal();
+        let decoded = decode(&encode(&samples), samples.len());
+        assert_eq!(decoded.len(), samples.len());
+
+        // The step size starts at its minimum and adapts within a few samples
+        let max_error = samples
+            .iter()
+            .zip(&decoded)
+            .skip(16)
+            .map(|(a, b)| (i32::from(*a) - i32::from(*b)).abs())
+            .max()
+            .unwrap();
+        assert!(max_error < 500, "max error {}", max_error);
+    }
+
+    #[test]
+    fn decode_stops_at_sample_count() {
+        let samples = signal();
+        let encoded = encode(&samples);
+        assert_eq!(
+            decode(&encoded, 1000),
+            decode(&encoded, samples.len())[..1000]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+
+/// Whether every output exists and is newer than `source`, so it doesn't have to be regenerated
+pub fn is_up_to_date(outputs: &[&Path], source: &Path) -> bool {
+    let Ok(source) = fs::metadata(source).and_then(|m| m.modified()) else {
+        return false;
+    };
+    outputs.iter().all(|output| {
+        fs::metadata(output)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= source)
+    })
+}
+
+/// Replaces the file with `contents` so it either holds the old or the new contents, even when
+/// the process crashes or the disk fills up halfway. The data is written to a temp file next to
+/// it, flushed to disk and then renamed over the original.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow!("Invalid file path: {}", path.display()))?;
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let mut f = File::create(&tmp_path)?;
+    f.write_all(contents)?;
+    f.sync_all()?;
+    drop(f);
+    fs::rename(&tmp_path, path)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
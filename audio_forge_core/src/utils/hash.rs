@@ -0,0 +1,140 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Result};
+
+/// Jenkins one-at-a-time hash as used by RAGE for every name lookup (streams, sounds, containers,...).
+/// The input is lowercased before hashing, just like the game does.
+pub fn joaat(input: &str) -> u32 {
+    let mut hash: u32 = 0;
+    for byte in input.bytes() {
+        hash = hash.wrapping_add(byte.to_ascii_lowercase() as u32);
+        hash = hash.wrapping_add(hash << 10);
+        hash ^= hash >> 6;
+    }
+    hash = hash.wrapping_add(hash << 3);
+    hash ^= hash >> 11;
+    hash = hash.wrapping_add(hash << 15);
+    hash
+}
+
+/// Hash of a name that may be a `hash_XXXXXXXX` placeholder, as given to streams imported from
+/// binary files whose names are unknown. Placeholders keep their value instead of being hashed.
+pub fn name_hash(name: &str) -> u32 {
+    match name.strip_prefix("hash_") {
+        Some(_) => name
+            .parse::<Hash>()
+            .map_or_else(|_| joaat(name), |h| h.value()),
+        None => joaat(name),
+    }
+}
+
+/// A RAGE name hash, optionally with the name it was computed from.
+///
+/// Parsed from `hash_XXXXXXXX`, `0xXXXXXXXX` or 8 hex digits, anything else is treated as a
+/// name and hashed with [`joaat`]. Serialized as the name when known, `hash_XXXXXXXX` otherwise.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hash {
+    value: u32,
+    name: Option<String>,
+}
+
+impl Hash {
+    pub fn from_name(name: &str) -> Self {
+        Self {
+            value: joaat(name),
+            name: Some(name.to_string()),
+        }
+    }
+
+    pub fn from_value(value: u32) -> Self {
+        Self { value, name: None }
+    }
+
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// `hash_XXXXXXXX` form as used by CodeWalker
+    pub fn hex(&self) -> String {
+        format!("hash_{:08X}", self.value)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(anyhow!("A hash can't be empty"));
+        }
+
+        let hex = s
+            .strip_prefix("hash_")
+            .or_else(|| s.strip_prefix("0x"))
+            .or_else(|| s.strip_prefix("0X"));
+        match hex {
+            Some(hex) => u32::from_str_radix(hex, 16)
+                .map(Hash::from_value)
+                .map_err(|_| anyhow!("Invalid hex hash: {}", s)),
+            // Older projects store the bare hex value
+            None if s.len() == 8 && s.chars().all(|c| c.is_ascii_hexdigit()) => {
+                Ok(Hash::from_value(u32::from_str_radix(s, 16)?))
+            }
+            None => Ok(Hash::from_name(s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Hash {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Hash> for String {
+    fn from(hash: Hash) -> Self {
+        hash.to_string()
+    }
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{}", self.hex()),
+        }
+    }
+}
+
+// Two hashes are the same when their values match, whether the name is known or not
+impl PartialEq for Hash {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Hash {}
+
+impl PartialOrd for Hash {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hash {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod adpcm;
+pub mod fs;
+pub mod hash;
+pub mod transcoder;
+pub mod wav;
+pub mod xml;
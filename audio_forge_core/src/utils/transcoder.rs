@@ -0,0 +1,637 @@
+extern crate ffmpeg_next as ffmpeg;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use ffmpeg_next::{codec, encoder, filter, format, frame, media, software, ChannelLayout};
+
+use crate::{jobs::JobContext, utils::wav::WavInfo};
+
+/// Step of transcoding a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Probe,
+    Decode,
+    Filter,
+    Encode,
+    Write,
+}
+
+impl ToString for Stage {
+    fn to_string(&self) -> String {
+        match self {
+            Stage::Probe => String::from("Probing"),
+            Stage::Decode => String::from("Decoding"),
+            Stage::Filter => String::from("Filtering"),
+            Stage::Encode => String::from("Encoding"),
+            Stage::Write => String::from("Writing"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TranscodeErrorKind {
+    Ffmpeg(ffmpeg::Error),
+    NoAudioStream,
+    /// ffmpeg was built without the named codec or filter
+    Unsupported(&'static str),
+    Cancelled,
+}
+
+/// Error of one of the transcoding stages of a file
+#[derive(Debug)]
+pub struct TranscodeError {
+    pub path: PathBuf,
+    pub stage: Stage,
+    pub kind: TranscodeErrorKind,
+}
+
+impl TranscodeError {
+    fn new(path: &Path, stage: Stage, kind: TranscodeErrorKind) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            stage,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} failed: ",
+            self.stage.to_string(),
+            self.path.display()
+        )?;
+        match &self.kind {
+            TranscodeErrorKind::Ffmpeg(err) => write!(f, "{}", err),
+            TranscodeErrorKind::NoAudioStream => write!(f, "the file has no audio stream"),
+            TranscodeErrorKind::Unsupported(name) => write!(f, "ffmpeg has no {} support", name),
+            TranscodeErrorKind::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for TranscodeError {}
+
+// Attaches the file and stage to ffmpeg errors
+trait AtStage<T> {
+    fn at(self, stage: Stage, path: &Path) -> Result<T, TranscodeError>;
+}
+
+impl<T> AtStage<T> for Result<T, ffmpeg::Error> {
+    fn at(self, stage: Stage, path: &Path) -> Result<T, TranscodeError> {
+        self.map_err(|err| TranscodeError::new(path, stage, TranscodeErrorKind::Ffmpeg(err)))
+    }
+}
+
+fn check_cancelled(job: &JobContext, stage: Stage, path: &Path) -> Result<(), TranscodeError> {
+    if job.is_cancelled() {
+        return Err(TranscodeError::new(
+            path,
+            stage,
+            TranscodeErrorKind::Cancelled,
+        ));
+    }
+    Ok(())
+}
+
+fn filter_context<'a>(
+    graph: &'a mut filter::Graph,
+    name: &str,
+) -> Result<filter::Context<'a>, ffmpeg::Error> {
+    graph.get(name).ok_or(ffmpeg::Error::FilterNotFound)
+}
+
+fn filter(
+    spec: &str,
+    decoder: &codec::decoder::Audio,
+    encoder: &codec::encoder::Audio,
+) -> Result<filter::Graph, ffmpeg_next::Error> {
+    let mut filter = filter::Graph::new();
+
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        decoder.time_base(),
+        decoder.rate(),
+        decoder.format().name(),
+        decoder.channel_layout().bits()
+    );
+
+    let abuffer = filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+    let abuffersink = filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+    filter.add(&abuffer, "in", &args)?;
+    filter.add(&abuffersink, "out", "")?;
+
+    {
+        let mut out = filter_context(&mut filter, "out")?;
+
+        out.set_sample_format(encoder.format());
+        out.set_channel_layout(encoder.channel_layout());
+        out.set_sample_rate(encoder.rate());
+    }
+
+    filter.output("in", 0)?.input("out", 0)?.parse(spec)?;
+    filter.validate()?;
+
+    if let Some(codec) = encoder.codec() {
+        if !codec
+            .capabilities()
+            .contains(ffmpeg_next::codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE)
+        {
+            filter_context(&mut filter, "out")?
+                .sink()
+                .set_frame_size(encoder.frame_size());
+        }
+    }
+
+    Ok(filter)
+}
+
+// Transcoder from the best audio stream of `ictx` to 16-bit PCM in `octx`
+fn transcoder(
+    path: &Path,
+    ictx: &mut format::context::Input,
+    octx: &mut format::context::Output,
+    filter_spec: &str,
+) -> Result<Transcoder, TranscodeError> {
+    let input = ictx
+        .streams()
+        .best(media::Type::Audio)
+        .ok_or(TranscodeError::new(
+            path,
+            Stage::Probe,
+            TranscodeErrorKind::NoAudioStream,
+        ))?;
+    let context = ffmpeg_next::codec::context::Context::from_parameters(input.parameters())
+        .at(Stage::Decode, path)?;
+
+    let mut decoder = context.decoder().audio().at(Stage::Decode, path)?;
+    decoder
+        .set_parameters(input.parameters())
+        .at(Stage::Decode, path)?;
+    if decoder.channel_layout().is_empty() {
+        decoder.set_channel_layout(ChannelLayout::default(decoder.channels().into()));
+    }
+    let channel_layout = decoder.channel_layout();
+
+    let unsupported =
+        |name| TranscodeError::new(path, Stage::Encode, TranscodeErrorKind::Unsupported(name));
+    let codec = encoder::find(codec::Id::PCM_S16LE)
+        .ok_or_else(|| unsupported("16-bit PCM"))?
+        .audio()
+        .at(Stage::Encode, path)?;
+    let global = octx
+        .format()
+        .flags()
+        .contains(ffmpeg_next::format::flag::Flags::GLOBAL_HEADER);
+
+    // Output config
+    octx.set_metadata(ictx.metadata().to_owned());
+    let mut ost = octx.add_stream(codec).at(Stage::Write, path)?;
+
+    let context = ffmpeg_next::codec::context::Context::from_parameters(ost.parameters())
+        .at(Stage::Encode, path)?;
+    let mut encoder = context.encoder().audio().at(Stage::Encode, path)?;
+    if global {
+        encoder.set_flags(ffmpeg_next::codec::flag::Flags::GLOBAL_HEADER);
+    }
+
+    // Set base encoder settings
+    encoder.set_rate(decoder.rate() as i32);
+    encoder.set_channel_layout(channel_layout);
+    encoder.set_channels(channel_layout.channels());
+    encoder.set_format(
+        codec
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .ok_or_else(|| unsupported("16-bit PCM sample format"))?,
+    );
+    encoder.set_bit_rate(decoder.bit_rate());
+    encoder.set_max_bit_rate(decoder.max_bit_rate());
+
+    encoder.set_time_base((1, decoder.rate() as i32));
+    ost.set_time_base((1, decoder.rate() as i32));
+    let encoder = encoder.open_as(codec).at(Stage::Encode, path)?;
+    ost.set_parameters(&encoder);
+
+    let filter = filter(filter_spec, &decoder, &encoder).at(Stage::Filter, path)?;
+
+    let in_time_base = decoder.time_base();
+    let out_time_base = ost.time_base();
+
+    Ok(Transcoder {
+        path: path.to_path_buf(),
+        stream: input.index(),
+        decoder,
+        encoder,
+        in_time_base,
+        out_time_base,
+        filter,
+    })
+}
+
+/// Basic properties of an audio file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Reads the properties of the best audio stream of the file without decoding it
+pub fn probe(path: &Path) -> Result<AudioInfo, TranscodeError> {
+    let ictx = format::input(&path).at(Stage::Probe, path)?;
+    let input = ictx
+        .streams()
+        .best(media::Type::Audio)
+        .ok_or(TranscodeError::new(
+            path,
+            Stage::Probe,
+            TranscodeErrorKind::NoAudioStream,
+        ))?;
+    let context =
+        codec::context::Context::from_parameters(input.parameters()).at(Stage::Probe, path)?;
+    let decoder = context.decoder().audio().at(Stage::Probe, path)?;
+
+    Ok(AudioInfo {
+        sample_rate: decoder.rate(),
+        channels: decoder.channels(),
+    })
+}
+
+/// Exact amount of samples per channel. 16-bit PCM wav files are counted from their header,
+/// anything else is decoded as the container duration is only an estimate.
+pub fn count_samples(path: &Path, job: &JobContext) -> Result<u64, TranscodeError> {
+    if let Ok(info) = WavInfo::read(path) {
+        if info.is_pcm16() {
+            return Ok(info.sample_count);
+        }
+    }
+
+    let mut count = 0;
+    decode_s16(path, job, |samples, channels| {
+        count += (samples.len() / channels) as u64;
+    })?;
+    Ok(count)
+}
+
+pub fn validate_audio_file(path: &Path) -> Result<(), TranscodeError> {
+    probe(path)?;
+    Ok(())
+}
+
+// Duration of the input in seconds, 0 when unknown
+fn duration(ictx: &format::context::Input) -> f64 {
+    (ictx.duration() as f64 / f64::from(ffmpeg_next::ffi::AV_TIME_BASE)).max(0.0)
+}
+
+// Reports how far into the input the packet is
+fn report_progress(
+    job: &JobContext,
+    duration: f64,
+    stream: &ffmpeg::Stream,
+    packet: &ffmpeg::Packet,
+) {
+    if let Some(pts) = packet.pts() {
+        if duration > 0.0 {
+            job.set_progress((pts as f64 * f64::from(stream.time_base()) / duration) as f32);
+        }
+    }
+}
+
+/// Transcodes the audio of `input` to a 16-bit PCM wav file at `output`. Nothing is left at
+/// `output` when this fails.
+pub fn encode_to_wav(input: &Path, output: &Path, job: &JobContext) -> Result<(), TranscodeError> {
+    let result = write_wav(input, output, job);
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+fn write_wav(input: &Path, output: &Path, job: &JobContext) -> Result<(), TranscodeError> {
+    let mut ictx = format::input(&input).at(Stage::Probe, input)?;
+    let mut octx = format::output_as(&output, "wav").at(Stage::Write, input)?;
+    let duration = duration(&ictx);
+
+    let transcoder = transcoder(input, &mut ictx, &mut octx, "anull")?;
+
+    octx.write_header().at(Stage::Write, input)?;
+
+    let mut process = TranscoderProcess { transcoder, octx };
+
+    for (stream, mut packet) in ictx.packets() {
+        check_cancelled(job, Stage::Decode, input)?;
+        process.process_packet(&stream, &mut packet)?;
+        report_progress(job, duration, &stream, &packet);
+    }
+
+    process.cleanup()?;
+    job.set_progress(1.0);
+    Ok(())
+}
+
+/// Where the samples of an output come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelSource {
+    /// A single channel of the source. Sources with fewer channels use their last channel, so a
+    /// mono file feeds every output.
+    Channel(usize),
+    /// The average of all channels of the source
+    Downmix,
+}
+
+impl ChannelSource {
+    // Sample of this source in an interleaved frame
+    fn sample(&self, frame: &[i16]) -> i16 {
+        match self {
+            ChannelSource::Channel(channel) => frame[(*channel).min(frame.len() - 1)],
+            ChannelSource::Downmix => {
+                let sum: i32 = frame.iter().map(|s| i32::from(*s)).sum();
+                (sum / frame.len() as i32) as i16
+            }
+        }
+    }
+}
+
+/// A source of the input file and where its samples go
+pub struct ChannelOutput<'a> {
+    pub source: ChannelSource,
+    /// Receives the mono 16-bit samples of the source
+    pub samples: &'a mut Vec<i16>,
+}
+
+/// Decodes `input` once and stores each requested channel in its output, any number of outputs
+/// can read from the same channel. Returns the sample rate.
+pub fn split_channels(
+    input: &Path,
+    outputs: &mut [ChannelOutput],
+    job: &JobContext,
+) -> Result<u32, TranscodeError> {
+    let sources: Vec<ChannelSource> = outputs.iter().map(|o| o.source).collect();
+    let mut buffers: Vec<Vec<i16>> = vec![vec![]; outputs.len()];
+
+    let sample_rate = decode_s16(input, job, |samples, channels| {
+        for (source, buffer) in sources.iter().zip(buffers.iter_mut()) {
+            buffer.extend(samples.chunks_exact(channels).map(|f| source.sample(f)));
+        }
+    })?;
+
+    for (output, samples) in outputs.iter_mut().zip(buffers) {
+        *output.samples = samples;
+    }
+    Ok(sample_rate)
+}
+
+// Decodes the best audio stream of `input` to interleaved 16-bit samples, `on_samples` gets every
+// decoded block together with the channel count. Returns the sample rate.
+fn decode_s16(
+    input: &Path,
+    job: &JobContext,
+    mut on_samples: impl FnMut(&[i16], usize),
+) -> Result<u32, TranscodeError> {
+    let mut ictx = format::input(&input).at(Stage::Probe, input)?;
+    let duration = duration(&ictx);
+    let stream = ictx
+        .streams()
+        .best(media::Type::Audio)
+        .ok_or(TranscodeError::new(
+            input,
+            Stage::Probe,
+            TranscodeErrorKind::NoAudioStream,
+        ))?;
+    let stream_index = stream.index();
+
+    let context =
+        codec::context::Context::from_parameters(stream.parameters()).at(Stage::Decode, input)?;
+    let mut decoder = context.decoder().audio().at(Stage::Decode, input)?;
+    decoder
+        .set_parameters(stream.parameters())
+        .at(Stage::Decode, input)?;
+    if decoder.channel_layout().is_empty() {
+        decoder.set_channel_layout(ChannelLayout::default(decoder.channels().into()));
+    }
+    let layout = decoder.channel_layout();
+    let rate = decoder.rate();
+
+    let mut decoded = Decoded {
+        path: input,
+        layout,
+        channels: layout.channels().max(1) as usize,
+        resampler: software::resampling::Context::get(
+            decoder.format(),
+            layout,
+            rate,
+            format::Sample::I16(format::sample::Type::Packed),
+            layout,
+            rate,
+        )
+        .at(Stage::Filter, input)?,
+        samples: vec![],
+    };
+
+    for (stream, packet) in ictx.packets() {
+        check_cancelled(job, Stage::Decode, input)?;
+        if stream.index() != stream_index {
+            continue;
+        }
+        decoder.send_packet(&packet).at(Stage::Decode, input)?;
+        decoded.receive(&mut decoder, &mut on_samples)?;
+        report_progress(job, duration, &stream, &packet);
+    }
+    decoder.send_eof().at(Stage::Decode, input)?;
+    decoded.receive(&mut decoder, &mut on_samples)?;
+    decoded.flush(&mut on_samples)?;
+
+    job.set_progress(1.0);
+    Ok(rate)
+}
+
+// Converts decoded frames to interleaved 16-bit samples
+struct Decoded<'a> {
+    path: &'a Path,
+    layout: ChannelLayout,
+    channels: usize,
+    resampler: software::resampling::Context,
+    samples: Vec<i16>,
+}
+
+impl Decoded<'_> {
+    fn receive(
+        &mut self,
+        decoder: &mut codec::decoder::Audio,
+        on_samples: &mut impl FnMut(&[i16], usize),
+    ) -> Result<(), TranscodeError> {
+        let mut frame = frame::Audio::empty();
+        while decoder.receive_frame(&mut frame).is_ok() {
+            if frame.channel_layout().is_empty() {
+                frame.set_channel_layout(self.layout);
+            }
+            // A fresh frame, so the resampler allocates enough room for all samples
+            let mut converted = frame::Audio::empty();
+            self.resampler
+                .run(&frame, &mut converted)
+                .at(Stage::Filter, self.path)?;
+            self.emit(&converted, on_samples);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, on_samples: &mut impl FnMut(&[i16], usize)) -> Result<(), TranscodeError> {
+        let mut converted = frame::Audio::empty();
+        self.resampler
+            .flush(&mut converted)
+            .at(Stage::Filter, self.path)?;
+        self.emit(&converted, on_samples);
+        Ok(())
+    }
+
+    fn emit(&mut self, converted: &frame::Audio, on_samples: &mut impl FnMut(&[i16], usize)) {
+        if converted.samples() == 0 {
+            return;
+        }
+        // The plane can be padded, only the first samples * channels values are audio
+        let len = converted.samples() * self.channels * 2;
+        self.samples.clear();
+        self.samples.extend(
+            converted.data(0)[..len]
+                .chunks_exact(2)
+                .map(|s| i16::from_ne_bytes([s[0], s[1]])),
+        );
+        on_samples(&self.samples, self.channels);
+    }
+}
+
+struct Transcoder {
+    // Input file, for errors
+    path: PathBuf,
+    pub stream: usize,
+    decoder: codec::decoder::Audio,
+    encoder: codec::encoder::Audio,
+    pub in_time_base: ffmpeg_next::Rational,
+    out_time_base: ffmpeg_next::Rational,
+    filter: filter::Graph,
+}
+
+impl Transcoder {
+    fn send_frame_to_encoder(&mut self, frame: &ffmpeg_next::Frame) -> Result<(), TranscodeError> {
+        self.encoder.send_frame(frame).at(Stage::Encode, &self.path)
+    }
+
+    fn send_eof_to_encoder(&mut self) -> Result<(), TranscodeError> {
+        self.encoder.send_eof().at(Stage::Encode, &self.path)
+    }
+
+    fn receive_and_process_encoded_packets(
+        &mut self,
+        octx: &mut format::context::Output,
+    ) -> Result<(), TranscodeError> {
+        let mut encoded = ffmpeg_next::Packet::empty();
+        while self.encoder.receive_packet(&mut encoded).is_ok() {
+            encoded.set_stream(0);
+            encoded.rescale_ts(self.in_time_base, self.out_time_base);
+            encoded
+                .write_interleaved(octx)
+                .at(Stage::Write, &self.path)?;
+        }
+        Ok(())
+    }
+
+    fn add_frame_to_filter(&mut self, frame: &ffmpeg_next::Frame) -> Result<(), TranscodeError> {
+        filter_context(&mut self.filter, "in")
+            .and_then(|mut ctx| ctx.source().add(frame))
+            .at(Stage::Filter, &self.path)
+    }
+
+    fn flush_filter(&mut self) -> Result<(), TranscodeError> {
+        filter_context(&mut self.filter, "in")
+            .and_then(|mut ctx| ctx.source().flush())
+            .at(Stage::Filter, &self.path)
+    }
+
+    fn get_and_process_filtered_frames(
+        &mut self,
+        octx: &mut format::context::Output,
+    ) -> Result<(), TranscodeError> {
+        let mut filtered = frame::Audio::empty();
+        while filter_context(&mut self.filter, "out")
+            .at(Stage::Filter, &self.path)?
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            self.send_frame_to_encoder(&filtered)?;
+            self.receive_and_process_encoded_packets(octx)?;
+        }
+        Ok(())
+    }
+
+    fn send_packet_to_decoder(
+        &mut self,
+        packet: &ffmpeg_next::Packet,
+    ) -> Result<(), TranscodeError> {
+        self.decoder
+            .send_packet(packet)
+            .at(Stage::Decode, &self.path)
+    }
+
+    fn send_eof_to_decoder(&mut self) -> Result<(), TranscodeError> {
+        self.decoder.send_eof().at(Stage::Decode, &self.path)
+    }
+
+    fn receive_and_process_decoded_frames(
+        &mut self,
+        octx: &mut format::context::Output,
+    ) -> Result<(), TranscodeError> {
+        let mut frame = frame::Audio::empty();
+        while self.decoder.receive_frame(&mut frame).is_ok() {
+            let timestamp = frame.timestamp();
+            frame.set_pts(timestamp);
+            self.add_frame_to_filter(&frame)?;
+            self.get_and_process_filtered_frames(octx)?;
+        }
+        Ok(())
+    }
+}
+
+struct TranscoderProcess {
+    pub transcoder: Transcoder,
+    pub octx: format::context::Output,
+}
+
+impl TranscoderProcess {
+    fn process_packet(
+        &mut self,
+        stream: &ffmpeg::Stream,
+        packet: &mut ffmpeg::Packet,
+    ) -> Result<(), TranscodeError> {
+        if stream.index() == self.transcoder.stream {
+            packet.rescale_ts(stream.time_base(), self.transcoder.in_time_base);
+            self.transcoder.send_packet_to_decoder(packet)?;
+            self.transcoder
+                .receive_and_process_decoded_frames(&mut self.octx)?;
+        }
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> Result<(), TranscodeError> {
+        // Cleanup the output context
+        self.transcoder.send_eof_to_decoder()?;
+        self.transcoder
+            .receive_and_process_decoded_frames(&mut self.octx)?;
+
+        self.transcoder.flush_filter()?;
+        self.transcoder
+            .get_and_process_filtered_frames(&mut self.octx)?;
+
+        self.transcoder.send_eof_to_encoder()?;
+        self.transcoder
+            .receive_and_process_encoded_packets(&mut self.octx)?;
+
+        self.octx
+            .write_trailer()
+            .at(Stage::Write, &self.transcoder.path)
+    }
+}
@@ -0,0 +1,483 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Format and length of a wav file, read from its header without loading the samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    pub format_tag: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    /// Amount of samples per channel
+    pub sample_count: u64,
+}
+
+impl WavInfo {
+    pub fn read(path: &Path) -> Result<WavInfo> {
+        WavInfo::read_header(path).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    fn read_header(path: &Path) -> Result<WavInfo> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut f = BufReader::new(file);
+
+        let mut riff = [0u8; 12];
+        f.read_exact(&mut riff)?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(anyhow!("not a RIFF/WAVE file"));
+        }
+
+        let mut pos = 12;
+        let mut format: Option<(u16, u16, u32, u16)> = None;
+        let mut header = [0u8; 8];
+        while f.read_exact(&mut header).is_ok() {
+            pos += 8;
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+            match &header[0..4] {
+                b"fmt " => {
+                    let mut chunk = vec![0u8; size as usize];
+                    f.read_exact(&mut chunk)?;
+                    if chunk.len() < 16 {
+                        return Err(anyhow!("fmt chunk is too small"));
+                    }
+                    format = Some((
+                        u16::from_le_bytes([chunk[0], chunk[1]]),
+                        u16::from_le_bytes([chunk[2], chunk[3]]),
+                        u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                        u16::from_le_bytes([chunk[14], chunk[15]]),
+                    ));
+                    f.seek_relative((size & 1) as i64)?;
+                }
+                b"data" => {
+                    let (format_tag, channels, sample_rate, bits_per_sample) =
+                        format.ok_or(anyhow!("data chunk before the fmt chunk"))?;
+                    let frame_size = channels as u64 * (bits_per_sample as u64 / 8);
+                    if frame_size == 0 {
+                        return Err(anyhow!("invalid fmt chunk"));
+                    }
+                    // Streamed or truncated files can claim more data than there is
+                    let data_size = size.min(file_len.saturating_sub(pos));
+                    return Ok(WavInfo {
+                        format_tag,
+                        channels,
+                        sample_rate,
+                        bits_per_sample,
+                        sample_count: data_size / frame_size,
+                    });
+                }
+                _ => f.seek_relative((size + (size & 1)) as i64)?,
+            }
+            pos += size + (size & 1);
+        }
+        Err(anyhow!("missing data chunk"))
+    }
+
+    /// Whether the samples are 16-bit PCM, the only format [`Wav`] can read
+    pub fn is_pcm16(&self) -> bool {
+        (self.format_tag == WAVE_FORMAT_PCM || self.format_tag == WAVE_FORMAT_EXTENSIBLE)
+            && self.bits_per_sample == 16
+    }
+}
+
+/// Loop and cue points stored by DAWs in the `smpl`, `cue ` and `LIST`/`adtl` chunks. Positions
+/// are in samples per channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WavCues {
+    /// Start and (exclusive) end of the first sample loop
+    pub sample_loop: Option<(u64, u64)>,
+    pub cues: Vec<WavCue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WavCue {
+    pub id: u32,
+    pub position: u64,
+    pub label: Option<String>,
+}
+
+impl WavCues {
+    /// Reads the cues of a wav file without loading the samples, other files have none
+    pub fn read(path: &Path) -> Result<WavCues> {
+        File::open(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|f| WavCues::read_chunks(BufReader::new(f)))
+            .map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    fn read_chunks(mut f: impl Read + Seek) -> Result<WavCues> {
+        let mut riff = [0u8; 12];
+        if f.read_exact(&mut riff).is_err() || &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Ok(WavCues::default());
+        }
+
+        let mut cues = WavCues::default();
+        let mut labels = vec![];
+        let mut header = [0u8; 8];
+        while f.read_exact(&mut header).is_ok() {
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+            let id = [header[0], header[1], header[2], header[3]];
+            if !matches!(&id, b"smpl" | b"cue " | b"LIST") {
+                f.seek(SeekFrom::Current((size + (size & 1)) as i64))?;
+                continue;
+            }
+
+            // The size isn't trusted, the chunk only grows as far as the file goes
+            let mut chunk = vec![];
+            f.by_ref().take(size).read_to_end(&mut chunk)?;
+            if chunk.len() as u64 != size {
+                return Err(anyhow!(
+                    "the {} chunk is truncated",
+                    String::from_utf8_lossy(&id)
+                ));
+            }
+            f.seek(SeekFrom::Current((size & 1) as i64))?;
+            match &id {
+                b"smpl" => cues.sample_loop = parse_sample_loop(&chunk),
+                b"cue " => cues.cues = parse_cue_points(&chunk),
+                _ if chunk.starts_with(b"adtl") => labels = parse_labels(&chunk[4..]),
+                _ => {}
+            }
+        }
+
+        for (id, label) in labels {
+            if let Some(cue) = cues.cues.iter_mut().find(|c| c.id == id) {
+                cue.label = Some(label);
+            }
+        }
+        Ok(cues)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// The loop count is at offset 28, the loops follow the 36 byte header. Each loop is 24 bytes:
+// cue point id, type, start, inclusive end, fraction and play count.
+fn parse_sample_loop(chunk: &[u8]) -> Option<(u64, u64)> {
+    if read_u32(chunk, 28)? == 0 {
+        return None;
+    }
+    let start = read_u32(chunk, 36 + 8)?;
+    let end = read_u32(chunk, 36 + 12)?;
+    (end >= start).then_some((u64::from(start), u64::from(end) + 1))
+}
+
+// Cue count followed by 24 byte cue points: id, position, data chunk id, chunk start, block start
+// and the sample offset in the data chunk
+fn parse_cue_points(chunk: &[u8]) -> Vec<WavCue> {
+    let count = read_u32(chunk, 0).unwrap_or(0) as usize;
+    (0..count)
+        .map_while(|i| {
+            let offset = 4 + i * 24;
+            Some(WavCue {
+                id: read_u32(chunk, offset)?,
+                position: u64::from(read_u32(chunk, offset + 20)?),
+                label: None,
+            })
+        })
+        .collect()
+}
+
+// `labl` sub chunks of an `adtl` list, a cue id followed by a null terminated name
+fn parse_labels(list: &[u8]) -> Vec<(u32, String)> {
+    RiffChunks::new(list)
+        .filter(|(id, _)| *id == b"labl")
+        .filter_map(|(_, body)| {
+            let id = read_u32(body, 0)?;
+            let text = body[4..].split(|b| *b == 0).next()?;
+            Some((id, String::from_utf8_lossy(text).to_string()))
+        })
+        .collect()
+}
+
+/// 16-bit PCM wav file as produced by `transcoder::encode_to_wav`.
+/// Samples are stored interleaved when the file has more than 1 channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wav {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+impl Wav {
+    pub fn read(path: &Path) -> Result<Wav> {
+        let bytes = fs::read(path)?;
+        Wav::parse(&bytes).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Wav> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(anyhow!("not a RIFF/WAVE file"));
+        }
+
+        let mut format: Option<(u16, u16, u32, u16)> = None;
+        let mut data: Option<&[u8]> = None;
+
+        for (id, chunk) in RiffChunks::new(&bytes[12..]) {
+            match id {
+                b"fmt " => {
+                    if chunk.len() < 16 {
+                        return Err(anyhow!("fmt chunk is too small"));
+                    }
+                    format = Some((
+                        u16::from_le_bytes([chunk[0], chunk[1]]),
+                        u16::from_le_bytes([chunk[2], chunk[3]]),
+                        u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                        u16::from_le_bytes([chunk[14], chunk[15]]),
+                    ));
+                }
+                b"data" => data = Some(chunk),
+                _ => {}
+            }
+        }
+
+        let (format_tag, channels, sample_rate, bits_per_sample) =
+            format.ok_or(anyhow!("missing fmt chunk"))?;
+        if format_tag != WAVE_FORMAT_PCM && format_tag != WAVE_FORMAT_EXTENSIBLE {
+            return Err(anyhow!("unsupported wav format tag {:#06x}", format_tag));
+        }
+        if bits_per_sample != 16 {
+            return Err(anyhow!(
+                "only 16-bit PCM is supported, got {} bits per sample",
+                bits_per_sample
+            ));
+        }
+        let data = data.ok_or(anyhow!("missing data chunk"))?;
+
+        Ok(Wav {
+            channels,
+            sample_rate,
+            samples: data
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect(),
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data_size = self.samples.len() * 2;
+        let block_align = self.channels * 2;
+        let mut buf = Vec::with_capacity(44 + data_size);
+
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&((36 + data_size) as u32).to_le_bytes());
+        buf.extend_from_slice(b"WAVE");
+
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&16u32.to_le_bytes());
+        buf.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        buf.extend_from_slice(&self.channels.to_le_bytes());
+        buf.extend_from_slice(&self.sample_rate.to_le_bytes());
+        buf.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        buf.extend_from_slice(&block_align.to_le_bytes());
+        buf.extend_from_slice(&16u16.to_le_bytes());
+
+        buf.extend_from_slice(b"data");
+        buf.extend_from_slice(&(data_size as u32).to_le_bytes());
+        for sample in &self.samples {
+            buf.extend_from_slice(&sample.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Interleaves mono wav files with the same sample rate into a single multi channel wav
+    pub fn interleave(channels: &[Wav]) -> Result<Wav> {
+        if channels.is_empty() {
+            return Err(anyhow!("no channels to interleave"));
+        }
+        let sample_rate = channels[0].sample_rate;
+        if channels
+            .iter()
+            .any(|c| c.channels != 1 || c.sample_rate != sample_rate)
+        {
+            return Err(anyhow!(
+                "only mono wav files with the same sample rate can be interleaved"
+            ));
+        }
+
+        let sample_count = channels.iter().map(|c| c.samples.len()).max().unwrap_or(0);
+        let mut samples = Vec::with_capacity(sample_count * channels.len());
+        for i in 0..sample_count {
+            for channel in channels {
+                samples.push(channel.samples.get(i).copied().unwrap_or(0));
+            }
+        }
+
+        Ok(Wav {
+            channels: channels.len() as u16,
+            sample_rate,
+            samples,
+        })
+    }
+
+    /// Amount of samples per channel
+    pub fn sample_count(&self) -> usize {
+        if self.channels == 0 {
+            return 0;
+        }
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Sample closest to `position` where the sum of all channels changes its sign, searching at
+    /// most `max_distance` samples in both directions. The start and end of the file count as
+    /// crossings, `position` is returned when there is none in reach.
+    pub fn nearest_zero_crossing(&self, position: usize, max_distance: usize) -> usize {
+        let channels = self.channels.max(1) as usize;
+        let count = self.sample_count();
+        let frame = |i: usize| -> i32 {
+            self.samples[i * channels..(i + 1) * channels]
+                .iter()
+                .map(|s| i32::from(*s))
+                .sum()
+        };
+        let is_crossing = |i: usize| {
+            i == 0 || i == count || {
+                let (before, after) = (frame(i - 1), frame(i));
+                (before <= 0 && after >= 0) || (before >= 0 && after <= 0)
+            }
+        };
+
+        let position = position.min(count);
+        for distance in 0..=max_distance {
+            for i in [position.saturating_sub(distance), position + distance] {
+                if i <= count && is_crossing(i) {
+                    return i;
+                }
+            }
+        }
+        position
+    }
+}
+
+/// Iterator over the (id, body) pairs of the chunks in a RIFF body
+struct RiffChunks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> RiffChunks<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for RiffChunks<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.len() < 8 {
+            return None;
+        }
+        let id = &self.bytes[0..4];
+        let size = u32::from_le_bytes([self.bytes[4], self.bytes[5], self.bytes[6], self.bytes[7]])
+            as usize;
+        let end = (8 + size).min(self.bytes.len());
+        let body = &self.bytes[8..end];
+        // Chunks are word aligned, odd sized chunks have a padding byte
+        let next = (end + (size & 1)).min(self.bytes.len());
+        self.bytes = &self.bytes[next..];
+        Some((id, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn riff(chunks: &[Vec<u8>]) -> Cursor<Vec<u8>> {
+        let body = chunks.concat();
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(&body);
+        Cursor::new(bytes)
+    }
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn cue_chunks() -> Vec<Vec<u8>> {
+        // 36 byte header with one loop from 250 to 749 (inclusive)
+        let smpl = words(&[0, 0, 0, 60, 0, 0, 0, 1, 0, 1, 0, 250, 749, 0, 0]);
+        let cue = words(&[2, 1, 100, 0, 0, 0, 100, 2, 500, 0, 0, 0, 500]);
+        let mut labl = words(&[1]);
+        labl.extend_from_slice(b"intro\0");
+        let mut list = b"adtl".to_vec();
+        list.extend(chunk(b"labl", &labl));
+
+        vec![
+            chunk(b"fmt ", &[0; 16]),
+            chunk(b"smpl", &smpl),
+            chunk(b"data", &[0; 7]),
+            chunk(b"cue ", &cue),
+            chunk(b"LIST", &list),
+        ]
+    }
+
+    #[test]
+    fn reads_loop_and_labelled_cues() {
+        let cues = WavCues::read_chunks(riff(&cue_chunks())).unwrap();
+
+        assert_eq!(cues.sample_loop, Some((250, 750)));
+        assert_eq!(
+            cues.cues,
+            vec![
+                WavCue {
+                    id: 1,
+                    position: 100,
+                    label: Some(String::from("intro")),
+                },
+                WavCue {
+                    id: 2,
+                    position: 500,
+                    label: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn other_files_have_no_cues() {
+        let cues = WavCues::read_chunks(Cursor::new(b"OggS".to_vec())).unwrap();
+        assert_eq!(cues, WavCues::default());
+    }
+
+    #[test]
+    fn oversized_chunk_is_an_error() {
+        let mut bytes = riff(&cue_chunks()).into_inner();
+        bytes.extend_from_slice(b"cue ");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&words(&[1, 1]));
+
+        assert!(WavCues::read_chunks(Cursor::new(bytes)).is_err());
+    }
+}
@@ -13,6 +13,10 @@ impl<T: Serialize> Value<T> {
     pub fn new(val: T) -> Self {
         Self { value: val }
     }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -27,6 +31,10 @@ impl InlineValue {
             value: val.to_string(),
         }
     }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
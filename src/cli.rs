@@ -0,0 +1,279 @@
+// Headless mode, used when AudioForge is started with a command so packs can be built without
+// a display (e.g. on a build server).
+//
+// On failure a JSON object describing the error is printed to stdout and the process exits with
+// a non-zero code: 1 when the command failed, 2 when the arguments are invalid. Transcoding errors
+// also name the `file` and the `stage` that failed, builds whose XML doesn't match the generated
+// wav files name the XML `file` and list the `problems`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use audio_forge_core::{
+    jobs::JobContext,
+    project_mgmt::{
+        awc::{AwcOutputFormat, AwcPack, AwcPackType},
+        project::{ImportOptions, Project},
+        validation,
+        verification::VerificationError,
+    },
+    utils::transcoder::TranscodeError,
+};
+
+const USAGE: &str = "\
+Usage: audio_forge <command> <project> [options]
+
+Commands:
+  build <project> [--pack <name>] [--format binary|xml]
+      Generate the awc and dat54 files of every pack (or only --pack). A binary build of
+      every pack also assembles the FiveM resource.
+  import <project> <file>... [--pack <name>] [--type simple|radio] [--ignore-cues]
+      Import .awc/.awc.xml files as new packs, other audio files are transcoded and added
+      to --pack, which is created with --type when it doesn't exist yet. Loop points and
+      cue markers of wav files are kept unless --ignore-cues is given.
+  list <project>
+      List the packs and their entries.
+  rescan <project>
+      Recount the samples of every entry from its wav file and fix the wrong counts.
+  validate <project>
+      Check the project for problems that would break a build.";
+
+enum CliError {
+    Usage(String),
+    Failed(anyhow::Error),
+    Invalid(Vec<String>),
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(err: anyhow::Error) -> Self {
+        CliError::Failed(err)
+    }
+}
+
+struct Args {
+    positional: Vec<String>,
+    pack: Option<String>,
+    format: AwcOutputFormat,
+    pack_type: AwcPackType,
+    import_options: ImportOptions,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Args, CliError> {
+        let mut parsed = Args {
+            positional: vec![],
+            pack: None,
+            format: AwcOutputFormat::Binary,
+            pack_type: AwcPackType::Simple,
+            import_options: ImportOptions::default(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or(CliError::Usage(format!("{} needs a value", name)))
+            };
+            match arg.as_str() {
+                "--pack" => parsed.pack = Some(value("--pack")?),
+                "--format" => {
+                    parsed.format = match value("--format")?.as_str() {
+                        "binary" => AwcOutputFormat::Binary,
+                        "xml" => AwcOutputFormat::Xml,
+                        other => return Err(CliError::Usage(format!("Unknown format {}", other))),
+                    }
+                }
+                "--type" => {
+                    parsed.pack_type = match value("--type")?.as_str() {
+                        "simple" => AwcPackType::Simple,
+                        "radio" => AwcPackType::Radio,
+                        other => {
+                            return Err(CliError::Usage(format!("Unknown pack type {}", other)))
+                        }
+                    }
+                }
+                "--ignore-cues" => parsed.import_options.ignore_cues = true,
+                other if other.starts_with("--") => {
+                    return Err(CliError::Usage(format!("Unknown option {}", other)))
+                }
+                _ => parsed.positional.push(arg.clone()),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn project(&self) -> Result<Project, CliError> {
+        let path = self
+            .positional
+            .first()
+            .ok_or(CliError::Usage(String::from("Missing project directory")))?;
+        Ok(Project::open_project(Path::new(path))?)
+    }
+}
+
+/// Runs the command in `args` (without the program name) and returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let command = args[0].as_str();
+    let result = Args::parse(&args[1..]).and_then(|parsed| {
+        audio_forge_core::init()?;
+        match command {
+            "build" => build(&parsed),
+            "import" => import(&parsed),
+            "list" => list(&parsed),
+            "rescan" => rescan(&parsed),
+            "validate" => validate(&parsed),
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+            other => Err(CliError::Usage(format!("Unknown command {}", other))),
+        }
+    });
+
+    let (code, error) = match result {
+        Ok(()) => return 0,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("{}", USAGE);
+            (2, serde_json::json!({ "command": command, "error": msg }))
+        }
+        Err(CliError::Failed(err)) => {
+            let mut error =
+                serde_json::json!({ "command": command, "error": format!("{:#}", err) });
+            // Point at the audio file which couldn't be transcoded
+            if let Some(err) = err.downcast_ref::<TranscodeError>() {
+                error["file"] = serde_json::json!(err.path.display().to_string());
+                error["stage"] = serde_json::json!(err.stage.to_string());
+            }
+            if let Some(err) = err.downcast_ref::<VerificationError>() {
+                error["file"] = serde_json::json!(err.xml_path.display().to_string());
+                error["problems"] = serde_json::json!(err.problems);
+            }
+            (1, error)
+        }
+        Err(CliError::Invalid(problems)) => (
+            1,
+            serde_json::json!({
+                "command": command,
+                "error": "The project is invalid",
+                "problems": problems,
+            }),
+        ),
+    };
+    println!("{}", error);
+    code
+}
+
+fn pack_index(project: &Project, name: &str) -> Result<usize> {
+    project
+        .awc_info
+        .iter()
+        .position(|p| p.name == name)
+        .ok_or(anyhow!("No pack named {}", name))
+}
+
+fn build(args: &Args) -> Result<(), CliError> {
+    let project = args.project()?;
+    let problems = validation::validate_project(&project);
+    if !problems.is_empty() {
+        return Err(CliError::Invalid(problems));
+    }
+
+    let indices = match &args.pack {
+        Some(name) => vec![pack_index(&project, name)?],
+        None => (0..project.awc_info.len()).collect(),
+    };
+    for i in indices {
+        project.generate_awc_file(i, args.format, &JobContext::default())?;
+        project.generate_dat54_file(i, args.format)?;
+        println!("Built {}", project.awc_info[i].name);
+    }
+
+    if args.pack.is_none() && args.format == AwcOutputFormat::Binary {
+        let resource_dir = project.generate_fivem_resource(&JobContext::default())?;
+        println!("Assembled FiveM resource in {}", resource_dir.display());
+    }
+    Ok(())
+}
+
+fn import(args: &Args) -> Result<(), CliError> {
+    let mut project = args.project()?;
+    let files: Vec<PathBuf> = args.positional[1..].iter().map(PathBuf::from).collect();
+    if files.is_empty() {
+        return Err(CliError::Usage(String::from("No files to import")));
+    }
+
+    for file in files {
+        let file_name = file.to_string_lossy().to_lowercase();
+        if file_name.ends_with(".awc") || file_name.ends_with(".awc.xml") {
+            let pack = project.import_awc_pack(&file)?;
+            project.add_awc_pack(pack);
+            println!("Imported {}", file.display());
+            continue;
+        }
+
+        let pack_name = args.pack.as_ref().ok_or(CliError::Usage(format!(
+            "--pack is needed to import {}",
+            file.display()
+        )))?;
+        if pack_index(&project, pack_name).is_err() {
+            project.add_awc_pack(AwcPack {
+                name: pack_name.clone(),
+                pack_type: args.pack_type,
+                entries: vec![],
+            });
+        }
+        let index = pack_index(&project, pack_name)?;
+        let entry =
+            project.import_audio_file(index, &file, args.import_options, &JobContext::default())?;
+        project.awc_info[index].entries.push(entry);
+        println!("Imported {} into {}", file.display(), pack_name);
+    }
+
+    project.save()?;
+    Ok(())
+}
+
+fn list(args: &Args) -> Result<(), CliError> {
+    let project = args.project()?;
+    for pack in &project.awc_info {
+        println!(
+            "{} ({}, {} entries)",
+            pack.name,
+            pack.pack_type.to_string(),
+            pack.entries.len()
+        );
+        for entry in &pack.entries {
+            println!(
+                "  {} [{}, {}]",
+                entry.name,
+                entry.codec.to_string(),
+                entry.channel_mode.to_string()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn rescan(args: &Args) -> Result<(), CliError> {
+    let mut project = args.project()?;
+    let fixes = project.rescan_samples(&JobContext::default())?;
+    for fix in &fixes {
+        println!("{}/{}: {} -> {}", fix.pack, fix.entry, fix.old, fix.new);
+    }
+    project.apply_sample_counts(&fixes);
+    project.save()?;
+    println!("Fixed {} sample counts", fixes.len());
+    Ok(())
+}
+
+fn validate(args: &Args) -> Result<(), CliError> {
+    let project = args.project()?;
+    let problems = validation::validate_project(&project);
+    if !problems.is_empty() {
+        return Err(CliError::Invalid(problems));
+    }
+    println!("Project is valid");
+    Ok(())
+}
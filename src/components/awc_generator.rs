@@ -1,32 +1,34 @@
-use std::{cell::RefCell, fs, path::Path, rc::Rc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
 
-use anyhow::{anyhow, Result};
 use eframe::egui;
 use egui::{Button, DroppedFile, Id, Window};
 use egui_extras::{Column, TableBuilder};
-use ffmpeg_next::{
-    format::{self},
-    media,
-};
 use itertools::Itertools;
 use log::error;
 use strum::IntoEnumIterator;
 
-use crate::{
-    project_mgmt::awc::{self, AwcPackType},
-    state::State,
-    utils::transcoder,
+use audio_forge_core::{
+    jobs::JobContext,
+    project_mgmt::{
+        awc::{self, AwcCodec, AwcPackType, ChannelMode},
+        history::Command,
+        project::{ImportOptions, Project},
+    },
 };
 
-use super::inputs;
+use crate::state::{JobOutput, State};
+
+use super::{inputs, region_editor::RegionEditor};
 
 pub struct AwcGenerator {
     state: Rc<RefCell<State>>,
     active_pack: usize,
+    output_format: awc::AwcOutputFormat,
     creator_window_state: AwcPackCreation,
-    // Map of awc entry indexes where the header editor windows should be shown for
-    // TODO: Are we able to refactor to only store the active indexes?
-    header_editor_window: Vec<bool>,
+    // Pack and entry names of the open header editors, indexes shift when entries are removed
+    header_editors: Vec<(String, String)>,
+    region_editor: Option<RegionEditor>,
+    import_options: ImportOptions,
 }
 
 #[derive(Default)]
@@ -41,8 +43,11 @@ impl AwcGenerator {
         Self {
             state,
             active_pack: 0,
+            output_format: awc::AwcOutputFormat::default(),
             creator_window_state: AwcPackCreation::default(),
-            header_editor_window: vec![],
+            header_editors: vec![],
+            region_editor: None,
+            import_options: ImportOptions::default(),
         }
     }
 
@@ -74,47 +79,70 @@ impl AwcGenerator {
                             }
                         });
                 });
-                if ui.button("Create").clicked() {
+                let mut state = self.state.borrow_mut();
+                let Some(project) = state.active_project.as_ref() else {
+                    return;
+                };
+                let name = self.creator_window_state.name.trim();
+                let problem = if name.is_empty() {
+                    Some("The pack needs a name")
+                } else if project.awc_info.iter().any(|p| p.name == name) {
+                    Some("A pack with this name already exists")
+                } else {
+                    None
+                };
+                if let Some(problem) = problem {
+                    ui.colored_label(ui.visuals().warn_fg_color, problem);
+                }
+                if ui
+                    .add_enabled(problem.is_none(), egui::Button::new("Create"))
+                    .clicked()
+                {
                     let pack = awc::AwcPack {
-                        name: self.creator_window_state.name.clone(),
+                        name: name.to_string(),
                         pack_type: self.creator_window_state.pack_type,
                         entries: vec![],
                     };
-                    if let Some(project) = self.state.borrow_mut().active_project.as_mut() {
-                        project.add_awc_pack(pack);
-                        self.creator_window_state.visible = false;
-                    }
+                    let command = Command::add_pack(project, pack);
+                    state.edit(command);
+                    self.creator_window_state.visible = false;
                 }
             });
     }
 
-    fn edit_entry_header_window(&mut self, ctx: &egui::Context, awc_entry_index: usize) {
-        if self.header_editor_window.len() <= awc_entry_index {
-            return;
-        }
-
+    /// Shows the header editor of an entry, returns false once it is closed or the entry is gone
+    fn edit_entry_header_window(&self, ctx: &egui::Context, pack: &str, entry: &str) -> bool {
         let mut state = self.state.borrow_mut();
-        if state.active_project.is_none() {
-            return;
-        }
-        let project = state.active_project.as_mut().unwrap();
-        let awc_pack = &mut project.awc_info[self.active_pack];
-        let awc_entry = &mut awc_pack.entries[awc_entry_index];
+        let Some(project) = state.active_project.as_ref() else {
+            return false;
+        };
+        let Some((pack_index, entry_index)) = project
+            .awc_info
+            .iter()
+            .position(|p| p.name == pack)
+            .and_then(|p| {
+                let entries = &project.awc_info[p].entries;
+                Some((p, entries.iter().position(|e| e.name == entry)?))
+            })
+        else {
+            return false;
+        };
+        let catalogue = &project.catalogue;
+        let awc_entry = &project.awc_info[pack_index].entries[entry_index];
+        let mut headers = awc_entry.headers.clone();
+        let mut open = true;
 
         Window::new(format!("Edit entry headers {}", awc_entry.name))
+            .id(Id::new(("header_editor", pack, entry)))
             .title_bar(true)
             .collapsible(false)
             .resizable(false)
             .default_size([600.0, 300.0])
-            .open(&mut self.header_editor_window[awc_entry_index])
+            .open(&mut open)
             .show(ctx, |ui| {
-                let headers = &mut awc_entry.headers;
+                let headers = &mut headers;
                 // TODO: Can this be done via loops?
-                ui.horizontal(|ui| {
-                    let label = ui.label("Category");
-                    ui.text_edit_singleline(&mut headers.category)
-                        .labelled_by(label.id);
-                });
+                inputs::hash_picker(ui, "Category", &mut headers.category, &catalogue.categories, Some("Pick a category or type a name/hash, e.g. frontend_game"));
 
                 ui.horizontal(|ui| {
                     let label = ui.label("Volume");
@@ -125,14 +153,7 @@ impl AwcGenerator {
                         });
                 });
 
-                ui.horizontal(|ui| {
-                    let label = ui.label("Volume Curve Hash (AKA Rolloff Hash)");
-                    ui.text_edit_singleline(&mut headers.volume_curve)
-                        .labelled_by(label.id)
-                        .on_hover_ui(|ui| {
-                            ui.label("Distance attenuation curves");
-                        });
-                });
+                inputs::hash_picker(ui, "Volume Curve (AKA Rolloff)", &mut headers.volume_curve, &catalogue.volume_curves, Some("Distance attenuation curves, pick one or type a name/hash"));
 
                 inputs::drag_value(ui, "Volume Distance", &mut headers.volume_curve_distance, Some("0 - 65535, How for the sound can be heard"));
                 inputs::optional_drag_value(ui, "Doppler Factor", &mut headers.doppler_factor, Some("0 - 65535"));
@@ -143,6 +164,77 @@ impl AwcGenerator {
                 inputs::optional_drag_value(ui, "Echo y", &mut headers.echo_y, None);
                 inputs::optional_drag_value(ui, "Echo z", &mut headers.echo_z, None);
             });
+
+        if headers != awc_entry.headers {
+            let old = awc_entry.headers.clone();
+            state.edit_coalescing(Command::SetHeader {
+                pack: pack_index,
+                entry: entry_index,
+                old,
+                new: headers,
+            });
+        }
+        open
+    }
+
+    fn show_history_panel(&mut self, ctx: &egui::Context) {
+        let mut state = self.state.borrow_mut();
+        let mut undo_steps = 0;
+        let mut redo_steps = 0;
+
+        egui::SidePanel::right("awc_generator_history")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("History");
+                    if ui
+                        .add_enabled(state.history.can_undo(), Button::new("Undo"))
+                        .on_hover_text("Ctrl+Z")
+                        .clicked()
+                    {
+                        undo_steps = 1;
+                    }
+                    if ui
+                        .add_enabled(state.history.can_redo(), Button::new("Redo"))
+                        .on_hover_text("Ctrl+Shift+Z")
+                        .clicked()
+                    {
+                        redo_steps = 1;
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    // Clicking an edit undoes or redoes everything up to it
+                    let undo: Vec<&str> = state.history.undo_descriptions().collect();
+                    for (i, description) in undo.iter().enumerate() {
+                        if ui
+                            .selectable_label(i + 1 == undo.len(), *description)
+                            .clicked()
+                        {
+                            undo_steps = undo.len() - 1 - i;
+                        }
+                    }
+                    for (i, description) in state.history.redo_descriptions().enumerate() {
+                        if ui
+                            .add(
+                                egui::Label::new(egui::RichText::new(description).weak())
+                                    .sense(egui::Sense::click()),
+                            )
+                            .clicked()
+                        {
+                            redo_steps = i + 1;
+                        }
+                    }
+                });
+            });
+
+        for _ in 0..undo_steps {
+            state.undo();
+        }
+        for _ in 0..redo_steps {
+            state.redo();
+        }
     }
 
     fn show_awc_entry_table(&mut self, ui: &mut egui::Ui) {
@@ -150,10 +242,13 @@ impl AwcGenerator {
         if state.active_project.is_none() {
             return;
         }
-        let project = state.active_project.as_mut().unwrap();
+        let project = state.active_project.as_ref().unwrap();
         if project.awc_info.len() <= self.active_pack {
             return;
         }
+        // Edits are applied after the table is drawn
+        let mut commands = vec![];
+        let mut edit_region = None;
         egui::ScrollArea::horizontal().show(ui, |ui| {
             let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
 
@@ -164,6 +259,9 @@ impl AwcGenerator {
                 .column(Column::auto())
                 .column(Column::auto())
                 .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto())
                 .column(Column::remainder());
 
             table
@@ -175,7 +273,16 @@ impl AwcGenerator {
                         ui.strong("Looped");
                     });
                     header.col(|ui| {
-                        ui.strong("Headers");
+                        ui.strong("Codec");
+                    });
+                    header.col(|ui| {
+                        ui.strong("Channels");
+                    });
+                    header.col(|ui| {
+                        ui.strong("Size");
+                    });
+                    header.col(|ui| {
+                        ui.strong("Edit");
                     });
                     header.col(|ui| {
                         ui.label("");
@@ -186,32 +293,110 @@ impl AwcGenerator {
                         text_height,
                         project.awc_info[self.active_pack].entries.len(),
                         |row_index, mut row| {
-                            let entry = &mut project.awc_info[self.active_pack].entries[row_index];
+                            let entry = &project.awc_info[self.active_pack].entries[row_index];
                             row.col(|ui| {
                                 ui.label(&entry.name);
                             });
                             row.col(|ui| {
-                                ui.checkbox(&mut entry.looped, "Looped");
+                                let mut looped = entry.looped;
+                                if ui.checkbox(&mut looped, "Looped").changed() {
+                                    commands.push(Command::SetLooped {
+                                        pack: self.active_pack,
+                                        entry: row_index,
+                                        looped,
+                                    });
+                                }
+                            });
+                            row.col(|ui| {
+                                egui::ComboBox::from_id_source(
+                                    Id::new("awc_entry_codec").with(row_index),
+                                )
+                                .selected_text(entry.codec.to_string())
+                                .show_ui(ui, |ui| {
+                                    for option in AwcCodec::iter() {
+                                        if ui
+                                            .selectable_label(
+                                                entry.codec == option,
+                                                option.to_string(),
+                                            )
+                                            .clicked()
+                                            && entry.codec != option
+                                        {
+                                            commands.push(Command::SetCodec {
+                                                pack: self.active_pack,
+                                                entry: row_index,
+                                                old: entry.codec,
+                                                new: option,
+                                            });
+                                        }
+                                    }
+                                });
+                            });
+                            row.col(|ui| {
+                                egui::ComboBox::from_id_source(
+                                    Id::new("awc_entry_channel_mode").with(row_index),
+                                )
+                                .selected_text(entry.channel_mode.to_string())
+                                .show_ui(ui, |ui| {
+                                    for option in ChannelMode::iter() {
+                                        if ui
+                                            .selectable_label(
+                                                entry.channel_mode == option,
+                                                option.to_string(),
+                                            )
+                                            .clicked()
+                                            && entry.channel_mode != option
+                                        {
+                                            commands.push(Command::SetChannelMode {
+                                                pack: self.active_pack,
+                                                entry: row_index,
+                                                old: entry.channel_mode,
+                                                new: option,
+                                            });
+                                        }
+                                    }
+                                })
+                                .response
+                                .on_hover_text(format!(
+                                    "The source has {} channel(s)",
+                                    entry.channels()
+                                ));
+                            });
+                            row.col(|ui| {
+                                ui.label(format_size(entry.estimated_size()));
                             });
                             row.col(|ui| {
                                 if ui.button("Headers").clicked() {
-                                    if self.header_editor_window.len() <= row_index {
-                                        self.header_editor_window.resize(row_index + 1, false);
+                                    let pack = &project.awc_info[self.active_pack].name;
+                                    let editor = (pack.clone(), entry.name.clone());
+                                    if !self.header_editors.contains(&editor) {
+                                        self.header_editors.push(editor);
                                     }
-                                    self.header_editor_window[row_index] = true;
                                 };
+                                if ui.button("Range").clicked() {
+                                    edit_region = Some(row_index);
+                                }
                             });
                             row.col(|ui| {
                                 if ui.button("Delete").clicked() {
-                                    let _ = project.awc_info[self.active_pack]
-                                        .entries
-                                        .remove(row_index);
+                                    commands.push(Command::RemoveEntry {
+                                        pack: self.active_pack,
+                                        index: row_index,
+                                        entry: entry.clone(),
+                                    });
                                 };
                             });
                         },
                     );
                 });
         });
+
+        if let Some(entry) = edit_region {
+            self.region_editor = RegionEditor::open(&state, self.active_pack, entry);
+        }
+        for command in commands {
+            state.edit(command);
+        }
     }
 }
 
@@ -223,24 +408,36 @@ impl eframe::App for AwcGenerator {
         }
         self.creator_window_state.visible &= show_create_window;
 
+        // Drags and text edits are a single undo step until they are released
+        if !ctx.input(|i| i.pointer.any_down()) && ctx.memory(|m| m.focus().is_none()) {
+            self.state.borrow_mut().history.end_coalescing();
+        }
+
         let state = self.state.borrow();
         if state.active_project.is_none() {
             return;
         }
         let project = state.active_project.as_ref().unwrap();
         let is_awc_pack_selected = project.awc_info.is_empty();
+        // Undoing can remove the selected pack
+        if self.active_pack >= project.awc_info.len() {
+            self.active_pack = project.awc_info.len().saturating_sub(1);
+        }
 
-        if project.awc_info.len() > 0 {
-            let pack_count = project.awc_info[self.active_pack].entries.len();
-            drop(state);
+        drop(state);
 
-            for i in 0..pack_count {
-                self.edit_entry_header_window(ctx, i);
+        let mut editors = std::mem::take(&mut self.header_editors);
+        editors.retain(|(pack, entry)| self.edit_entry_header_window(ctx, pack, entry));
+        self.header_editors = editors;
+
+        if let Some(editor) = &mut self.region_editor {
+            if !editor.show(ctx, &mut self.state.borrow_mut()) {
+                self.region_editor = None;
             }
-        } else {
-            drop(state);
         }
 
+        self.show_history_panel(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 let state = self.state.borrow();
@@ -248,7 +445,6 @@ impl eframe::App for AwcGenerator {
                     return;
                 }
                 let project = state.active_project.as_ref().unwrap();
-                let project_loc = project.location.clone();
 
                 if project.awc_info.len() > 0 {
                     egui::ComboBox::from_id_source(Id::new("awc_generator_pack_selector"))
@@ -277,41 +473,82 @@ impl eframe::App for AwcGenerator {
                     });
                 }
 
+                if project.awc_info.len() > 0 {
+                    ui.label(format!(
+                        "Estimated size: {}",
+                        format_size(project.awc_info[self.active_pack].estimated_size())
+                    ));
+                }
+
                 if ui.button("New audio pack").clicked() {
                     self.creator_window_state.name = String::from("");
                     self.creator_window_state.visible = true;
                 }
+                let mut removed_pack = None;
+                if project.awc_info.len() > 0 && ui.button("Delete pack").clicked() {
+                    removed_pack = Some(Command::remove_pack(project, self.active_pack));
+                }
 
                 drop(state);
+                if let Some(command) = removed_pack {
+                    self.state.borrow_mut().edit(command);
+                    self.active_pack = 0;
+                }
+                if ui.button("Import .awc").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_title("Select an awc or CodeWalker awc.xml file")
+                        .add_filter("AudioWaveContainer", &["awc", "xml"])
+                        .pick_file()
+                    {
+                        self.spawn_job(format!("Import {}", path.display()), move |project, _| {
+                            Ok(JobOutput::ImportedPack(project.import_awc_pack(&path)?))
+                        });
+                    }
+                }
                 if !is_awc_pack_selected {
                     if ui.button("Add audio file").clicked() {
-                        // TODO: Make this usable in spawnable thread so render thread is not blocked
                         if let Some(paths) = rfd::FileDialog::new()
                             .set_title("Select to be added audio files")
                             .pick_files()
                         {
-                            for path in paths {
-                                if self::AwcGenerator::validate_file(&path).is_ok() {
-                                    let process = self.import_file(&path);
-                                    if process.is_err() {
-                                        error!("{:?}", process.unwrap_err());
-                                    }
-                                };
-                            }
+                            self.import_files(paths);
                         }
                     }
+                    ui.checkbox(
+                        &mut self.import_options.ignore_cues,
+                        "Ignore wav loop points",
+                    )
+                    .on_hover_text(
+                        "Don't take the loop region and markers from the smpl and cue \
+                         chunks of imported wav files",
+                    );
                 }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui.button("Generate FiveM resource").clicked() {
-                        let _ = fs::create_dir_all(project_loc.join("output/awc_resource"));
-                        let state = self.state.borrow();
-                        if state.active_project.is_none() {
-                            return;
-                        }
-                        let project = state.active_project.as_ref().unwrap();
-                        project.generate_awc_file(self.active_pack);
-                        // TODO: generate dat54 file
+                        self.spawn_job("Generate FiveM resource", |project, job| {
+                            let resource_dir = project.generate_fivem_resource(job)?;
+                            Ok(JobOutput::Built(resource_dir.display().to_string()))
+                        });
                     }
+                    if ui.button("Generate pack").clicked() {
+                        let (index, format) = (self.active_pack, self.output_format);
+                        self.spawn_job("Generate pack", move |project, job| {
+                            project.generate_awc_file(index, format, job)?;
+                            project.generate_dat54_file(index, format)?;
+                            Ok(JobOutput::Built(project.awc_info[index].name.clone()))
+                        });
+                    }
+                    egui::ComboBox::from_id_source(Id::new("awc_generator_output_format"))
+                        .selected_text(self.output_format.to_string())
+                        .show_ui(ui, |ui| {
+                            for option in awc::AwcOutputFormat::iter() {
+                                ui.selectable_value(
+                                    &mut self.output_format,
+                                    option,
+                                    option.to_string(),
+                                );
+                            }
+                        });
                 });
             });
 
@@ -322,57 +559,75 @@ impl eframe::App for AwcGenerator {
             self.show_awc_entry_table(ui);
             ui.set_min_height(ui.available_height());
 
-            ctx.input(|i| {
-                for file in i.raw.dropped_files.iter() {
-                    if let Some(path) = &file.path {
-                        if self::AwcGenerator::validate_file(path).is_ok() {
-                            let process = self.import_file(path);
-                            if process.is_err() {
-                                error!("{:?}", process.unwrap_err());
-                            }
-                        };
-                    };
-                }
+            let dropped: Vec<PathBuf> = ctx.input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|file| file.path.clone())
+                    .collect()
             });
+            if !dropped.is_empty() {
+                self.import_files(dropped);
+            }
         });
     }
 }
 
 impl AwcGenerator {
-    fn validate_file(path: &Path) -> Result<()> {
-        let input_format = format::input(&path)?;
-        let input = input_format.streams().best(media::Type::Audio);
-        if input.is_none() {
-            return Err(anyhow!(
-                "File does not contain audio stream: {}",
-                path.display().to_string()
-            ));
-        }
-        Ok(())
+    // Runs `work` in the background on a snapshot of the active project
+    fn spawn_job(
+        &self,
+        name: impl Into<String>,
+        work: impl FnOnce(&Project, &JobContext) -> anyhow::Result<JobOutput> + Send + 'static,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let Some(project) = state.active_project.clone() else {
+            return;
+        };
+        state.jobs.spawn(name, move |job| work(&project, job));
     }
 
-    fn import_file(&mut self, path: &Path) -> Result<()> {
+    // Transcodes every file in its own job, the entries are added once they are done
+    fn import_files(&self, paths: Vec<PathBuf>) {
         let mut state = self.state.borrow_mut();
-        let project = state.active_project.as_mut().unwrap();
-        let awc_pack = &project.awc_info[self.active_pack];
-
-        let entry_name = &path.file_stem().unwrap().to_string_lossy().to_string();
-        let output_dir = project
-            .location
-            .clone()
-            .join("awc_packs")
-            .join(awc_pack.name.clone());
-        fs::create_dir_all(output_dir.as_path())?;
-
-        transcoder::encode_to_wav(path, &output_dir)?;
-
-        let proj_loc = project.location.clone();
-        project.get_mut_entries_slice()[self.active_pack].add_entry(
-            &proj_loc,
-            &output_dir,
-            entry_name,
-        )?;
-
-        Ok(())
+        let Some(project) = state.active_project.clone() else {
+            return;
+        };
+        let project = Arc::new(project);
+        let index = self.active_pack;
+        let options = self.import_options;
+        let pack = project.awc_info[index].name.clone();
+
+        for path in paths {
+            // Entries are named after the file, the name is reserved until the job is done
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if state.is_importing(&pack, &name) {
+                error!("{} is already being imported into {}", name, pack);
+                continue;
+            }
+            let project = project.clone();
+            let job_pack = pack.clone();
+            let job = state
+                .jobs
+                .spawn(format!("Import {}", path.display()), move |job| {
+                    let entry = project.import_audio_file(index, &path, options, job)?;
+                    Ok(JobOutput::ImportedEntry {
+                        pack: job_pack,
+                        entry,
+                    })
+                });
+            state.reserve_import(job, pack.clone(), name);
+        }
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
     }
 }
@@ -1,5 +1,10 @@
 use egui::{Ui};
 
+use audio_forge_core::{
+    dat_files::catalogue::{self, CatalogueEntry},
+    utils::hash::Hash,
+};
+
 
 pub fn optional_drag_value<T: eframe::emath::Numeric>(
     ui: &mut Ui,
@@ -58,3 +63,80 @@ pub fn drag_value<T: eframe::emath::Numeric>(
         }
     });
 }
+
+// Text box editing a hash, the typed text is kept while editing and the hash only changes on
+// valid input
+fn hash_text_edit(ui: &mut Ui, id: egui::Id, val: &mut Hash) -> egui::Response {
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| val.to_string());
+
+    let res = ui.text_edit_singleline(&mut text);
+    if res.changed() {
+        if let Ok(hash) = text.parse() {
+            *val = hash;
+        }
+    }
+    if res.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, text));
+    } else {
+        ui.data_mut(|d| d.remove::<String>(id));
+    }
+    res
+}
+
+/// Searchable combo box over the catalogue entries, with a text box next to it for hashes that
+/// aren't in the catalogue
+pub fn hash_picker(
+    ui: &mut Ui,
+    label: &str,
+    val: &mut Hash,
+    entries: &[CatalogueEntry],
+    tooltip: Option<&str>,
+) {
+    ui.horizontal(|ui| {
+        let id = ui.make_persistent_id(label);
+        let search_id = id.with("search");
+        let label = ui.label(label);
+
+        let selected_text = match catalogue::find_name(entries, val) {
+            Some(name) => name.to_string(),
+            None => String::from("Custom"),
+        };
+        let res = egui::ComboBox::from_id_source(id.with("picker"))
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                let mut search = ui
+                    .data_mut(|d| d.get_temp::<String>(search_id))
+                    .unwrap_or_default();
+                ui.text_edit_singleline(&mut search).on_hover_text("Search");
+                let search_lower = search.to_lowercase();
+
+                for entry in entries
+                    .iter()
+                    .filter(|e| e.name.to_lowercase().contains(&search_lower))
+                {
+                    let hash = entry.hash();
+                    let selected = hash == *val;
+                    if ui
+                        .selectable_label(selected, format!("{} ({})", entry.name, hash.hex()))
+                        .clicked()
+                    {
+                        *val = hash;
+                    }
+                }
+                ui.data_mut(|d| d.insert_temp(search_id, search));
+            })
+            .response
+            .labelled_by(label.id);
+
+        hash_text_edit(ui, id.with("custom"), val);
+        ui.label(val.hex());
+
+        if let Some(tooltip_label) = tooltip {
+            res.on_hover_ui(|ui| {
+                ui.label(tooltip_label);
+            });
+        }
+    });
+}
@@ -2,3 +2,4 @@ pub mod awc_generator;
 pub mod inputs;
 pub mod occl_generator;
 pub mod project_selector;
+pub mod region_editor;
@@ -1,13 +1,18 @@
-use std::{cell::RefCell, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use eframe::egui;
 use egui::RichText;
 use log::error;
 
-use crate::{
-    project_mgmt::project::{add_to_recent_projects, Project},
-    state::State,
-};
+use anyhow::Result;
+use audio_forge_core::project_mgmt::{backup, project::Project};
+
+use crate::state::State;
 
 pub struct ProjectSelector {
     state: Rc<RefCell<State>>,
@@ -26,7 +31,7 @@ impl eframe::App for ProjectSelector {
                 ui.label(RichText::new("GTA V Audio Toolkit").size(24.0));
                 ui.add_space(10.0);
                 if ui.button("Open Project").clicked() {
-                    let select_res = Project::choose_project();
+                    let select_res = choose_project();
                     if select_res.is_err() {
                         // TODO: replace with error dialog
                         error!("{:?}", select_res.unwrap_err());
@@ -55,7 +60,7 @@ impl eframe::App for ProjectSelector {
 
                     for proj in recent_projects {
                         if ui.selectable_label(false, proj.to_string_lossy()).clicked() {
-                            let proj = Project::open_project(&proj);
+                            let proj = open_project(&proj);
                             if proj.is_err() {
                                 error!("failed to select recent project: {:?}", proj.unwrap_err());
                                 continue;
@@ -71,3 +76,77 @@ impl eframe::App for ProjectSelector {
         });
     }
 }
+
+// Opens directory picker
+fn choose_project() -> Result<Project> {
+    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+        let mut valid = Project::is_folder_a_project(path.as_path());
+        if !valid {
+            if fs::read_dir(path.as_path()).unwrap().count() > 0 {
+                if rfd::MessageDialog::new()
+                    .set_title("Create project in folder with files")
+                    .set_description("Are you sure you want to create a project in a folder which already contain files")
+                    .set_buttons(rfd::MessageButtons::YesNo)
+                    .show()
+                    == rfd::MessageDialogResult::Yes
+                    {
+                        Project::create_project(&path)?;
+                        valid = true;
+                    }
+            } else {
+                Project::create_project(&path)?;
+                valid = true;
+            }
+        }
+        if !valid {
+            return Err(anyhow::format_err!(""));
+        }
+        let proj = open_project(&path)?;
+        return Ok(proj);
+    }
+    Err(anyhow::format_err!(""))
+}
+
+// Opens the project, offering to restore the latest backup when info.json can't be read
+fn open_project(path: &Path) -> Result<Project> {
+    let err = match Project::open_project(path) {
+        Ok(project) => return Ok(project),
+        Err(err) => err,
+    };
+    let backups = backup::list_backups(path)?;
+    if !backup::is_parse_error(&err) || backups.is_empty() {
+        return Err(err);
+    }
+
+    let restore = rfd::MessageDialog::new()
+        .set_title("Failed to open project")
+        .set_description(&format!(
+            "info.json could not be read: {}\n\nRestore the most recent backup ({})?",
+            err,
+            backups[0].display()
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        == rfd::MessageDialogResult::Yes;
+    if !restore {
+        return Err(err);
+    }
+
+    backup::restore_backup(path, &backups[0])?;
+    Project::open_project(path)
+}
+
+fn add_to_recent_projects(frame: &mut eframe::Frame, path: PathBuf) {
+    let storage = frame.storage_mut().unwrap();
+    let recent_projects_str = storage.get_string("project_history");
+    let mut recent_projects = Vec::<PathBuf>::new();
+    if let Some(paths) = recent_projects_str {
+        recent_projects = serde_json::from_str(&paths).unwrap();
+    }
+    recent_projects.insert(0, path);
+    recent_projects.truncate(10);
+    storage.set_string(
+        "project_history",
+        serde_json::to_string(&recent_projects).unwrap(),
+    );
+}
@@ -0,0 +1,348 @@
+// Editor for the play range and loop region of an awc entry, shown on top of the waveform of its
+// wav file.
+
+use std::ops::RangeInclusive;
+
+use eframe::egui;
+use egui::{pos2, vec2, Align2, Color32, FontId, Rect, Sense, Stroke, Window};
+
+use audio_forge_core::{
+    project_mgmt::{
+        awc::{AwcEntry, AwcRegion},
+        history::Command,
+    },
+    utils::wav::Wav,
+};
+
+use crate::state::State;
+
+// How far markers snap to the next zero crossing, in samples
+const SNAP_DISTANCE: usize = 2000;
+// Distance in pixels in which a marker can be grabbed
+const GRAB_DISTANCE: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    PlayBegin,
+    PlayEnd,
+    LoopStart,
+    LoopEnd,
+}
+
+impl Marker {
+    fn position(&self, region: &AwcRegion) -> u64 {
+        match self {
+            Marker::PlayBegin => region.play_begin,
+            Marker::PlayEnd => region.play_end,
+            Marker::LoopStart => region.loop_start,
+            Marker::LoopEnd => region.loop_end,
+        }
+    }
+
+    // Positions the format chunk can store given the other markers, see `AwcEntry::format_region`
+    fn range(&self, region: &AwcRegion, samples: u64) -> RangeInclusive<u64> {
+        let max_offset = u64::from(u16::MAX);
+        match self {
+            Marker::PlayBegin => 0..=samples.min(u64::from(u8::MAX)),
+            Marker::PlayEnd => samples.saturating_sub(max_offset)..=samples,
+            Marker::LoopStart => region.play_begin..=(region.play_begin + max_offset).min(samples),
+            Marker::LoopEnd => region.play_end.saturating_sub(max_offset)..=region.play_end,
+        }
+    }
+
+    fn set_position(&self, region: &mut AwcRegion, position: u64) {
+        match self {
+            Marker::PlayBegin => region.play_begin = position,
+            Marker::PlayEnd => region.play_end = position,
+            Marker::LoopStart => region.loop_start = position,
+            Marker::LoopEnd => region.loop_end = position,
+        }
+    }
+}
+
+impl ToString for Marker {
+    fn to_string(&self) -> String {
+        match self {
+            Marker::PlayBegin => String::from("Play begin"),
+            Marker::PlayEnd => String::from("Play end"),
+            Marker::LoopStart => String::from("Loop start"),
+            Marker::LoopEnd => String::from("Loop end"),
+        }
+    }
+}
+
+// Pack and entry are remembered by name, removing entries or undoing edits shifts the indexes
+pub struct RegionEditor {
+    pack: String,
+    entry: String,
+    // Wav file of the entry, loaded when the editor is opened
+    wav: Result<Wav, String>,
+    // Min and max sample of every pixel column, recomputed when the width changes
+    waveform: Vec<(i16, i16)>,
+    dragging: Option<(Marker, u64)>,
+    snap: bool,
+}
+
+impl RegionEditor {
+    /// Loads the wav file of the entry, None when no project is open
+    pub fn open(state: &State, pack: usize, entry: usize) -> Option<Self> {
+        let project = state.active_project.as_ref()?;
+        let awc_pack = &project.awc_info[pack];
+        let awc_entry = &awc_pack.entries[entry];
+        let wav = Wav::read(&awc_entry.source_path(&project.location))
+            .map_err(|err| format!("{:#}", err));
+        Some(Self {
+            pack: awc_pack.name.clone(),
+            entry: awc_entry.name.clone(),
+            wav,
+            waveform: vec![],
+            dragging: None,
+            snap: true,
+        })
+    }
+
+    /// Shows the editor window, returns false once it was closed or the entry is gone
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut State) -> bool {
+        let Some(project) = state.active_project.as_ref() else {
+            return false;
+        };
+        let Some((pack_index, entry_index)) = project
+            .awc_info
+            .iter()
+            .position(|p| p.name == self.pack)
+            .and_then(|p| {
+                let entries = &project.awc_info[p].entries;
+                Some((p, entries.iter().position(|e| e.name == self.entry)?))
+            })
+        else {
+            return false;
+        };
+        let entry = &project.awc_info[pack_index].entries[entry_index];
+
+        let old = entry.region();
+        let mut region = old;
+        let mut release = false;
+        let mut open = true;
+
+        Window::new(format!("Edit play range and loop {}", entry.name))
+            .title_bar(true)
+            .collapsible(false)
+            .resizable(true)
+            .default_size([800.0, 300.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for marker in [Marker::PlayBegin, Marker::PlayEnd] {
+                        region_drag_value(ui, marker, &mut region, entry.samples());
+                    }
+                    ui.add_enabled_ui(entry.looped, |ui| {
+                        for marker in [Marker::LoopStart, Marker::LoopEnd] {
+                            region_drag_value(ui, marker, &mut region, entry.samples());
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.snap, "Snap to zero crossings");
+                    if ui.button("Reset").clicked() {
+                        region = AwcRegion {
+                            play_begin: 0,
+                            play_end: entry.samples(),
+                            loop_start: 0,
+                            loop_end: entry.samples(),
+                        };
+                        release = true;
+                    }
+                    if !entry.looped {
+                        ui.label("Enable \"Looped\" to edit the loop region");
+                    }
+                });
+                if let Err(err) = entry.format_region() {
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                }
+
+                match self.wav.as_ref().err().cloned() {
+                    Some(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+                    None => release |= self.waveform_view(ui, entry, &mut region),
+                }
+            });
+
+        // Dragged markers are only applied once they are released
+        if region != old && self.dragging.is_none() {
+            let command = Command::SetRegion {
+                pack: pack_index,
+                entry: entry_index,
+                old,
+                new: region,
+            };
+            match release {
+                true => state.edit(command),
+                false => state.edit_coalescing(command),
+            }
+        }
+        open
+    }
+
+    // Paints the waveform with the markers, returns true when a marker was released
+    fn waveform_view(
+        &mut self,
+        ui: &mut egui::Ui,
+        entry: &AwcEntry,
+        region: &mut AwcRegion,
+    ) -> bool {
+        let Ok(wav) = &self.wav else {
+            return false;
+        };
+        let (response, painter) =
+            ui.allocate_painter(vec2(ui.available_width(), 200.0), Sense::click_and_drag());
+        let rect = response.rect;
+        let count = wav.sample_count().max(1) as f32;
+        let x_of = |position: u64| rect.left() + rect.width() * position as f32 / count;
+        let position_at =
+            |x: f32| (((x - rect.left()) / rect.width()).clamp(0.0, 1.0) * count) as u64;
+
+        let columns = rect.width().max(1.0) as usize;
+        if self.waveform.len() != columns {
+            self.waveform = waveform(wav, columns);
+        }
+
+        let mut markers = vec![Marker::PlayBegin, Marker::PlayEnd];
+        if entry.looped {
+            markers.extend([Marker::LoopStart, Marker::LoopEnd]);
+        }
+
+        // Grab the closest marker, move it while dragging and snap it once released
+        if response.drag_started() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                self.dragging = markers
+                    .iter()
+                    .map(|m| (*m, (x_of(m.position(region)) - pointer.x).abs()))
+                    .filter(|(_, distance)| *distance <= GRAB_DISTANCE)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(marker, _)| (marker, marker.position(region)));
+            }
+        }
+        if let Some((marker, position)) = &mut self.dragging {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let range = marker.range(region, entry.samples());
+                *position = position_at(pointer.x).clamp(*range.start(), *range.end());
+            }
+            marker.set_position(region, *position);
+        }
+        let mut released = false;
+        if response.drag_released() {
+            if let Some((marker, position)) = self.dragging.take() {
+                let position = match self.snap {
+                    true => wav.nearest_zero_crossing(position as usize, SNAP_DISTANCE) as u64,
+                    false => position,
+                };
+                let range = marker.range(region, entry.samples());
+                let position = position.clamp(*range.start(), *range.end());
+                marker.set_position(region, position);
+                released = true;
+            }
+        }
+
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+        if entry.looped {
+            painter.rect_filled(
+                Rect::from_min_max(
+                    pos2(x_of(region.loop_start), rect.top()),
+                    pos2(x_of(region.loop_end), rect.bottom()),
+                ),
+                0.0,
+                Color32::from_rgba_unmultiplied(80, 160, 255, 40),
+            );
+        }
+
+        let center = rect.center().y;
+        let scale = rect.height() / 2.0 / f32::from(i16::MAX);
+        for (i, (min, max)) in self.waveform.iter().enumerate() {
+            let x = rect.left() + i as f32 + 0.5;
+            painter.line_segment(
+                [
+                    pos2(x, center - f32::from(*max) * scale),
+                    pos2(x, center - f32::from(*min) * scale),
+                ],
+                Stroke::new(1.0, visuals.text_color()),
+            );
+        }
+
+        // Dim everything outside of the play range
+        let dim = Color32::from_black_alpha(140);
+        painter.rect_filled(
+            Rect::from_min_max(
+                rect.left_top(),
+                pos2(x_of(region.play_begin), rect.bottom()),
+            ),
+            0.0,
+            dim,
+        );
+        painter.rect_filled(
+            Rect::from_min_max(pos2(x_of(region.play_end), rect.top()), rect.right_bottom()),
+            0.0,
+            dim,
+        );
+
+        for marker in markers {
+            let x = x_of(marker.position(region));
+            let color = match marker {
+                Marker::PlayBegin | Marker::PlayEnd => Color32::from_rgb(80, 200, 120),
+                Marker::LoopStart | Marker::LoopEnd => Color32::from_rgb(80, 160, 255),
+            };
+            painter.line_segment(
+                [pos2(x, rect.top()), pos2(x, rect.bottom())],
+                Stroke::new(2.0, color),
+            );
+            let (anchor, y) = match marker {
+                Marker::PlayBegin | Marker::LoopStart => (Align2::LEFT_TOP, rect.top()),
+                Marker::PlayEnd | Marker::LoopEnd => (Align2::RIGHT_BOTTOM, rect.bottom()),
+            };
+            painter.text(
+                pos2(x, y),
+                anchor,
+                marker.to_string(),
+                FontId::proportional(12.0),
+                color,
+            );
+        }
+
+        if response.hovered() && self.dragging.is_none() {
+            response.on_hover_text(format!(
+                "Drag the markers, positions are in samples at {} Hz",
+                wav.sample_rate
+            ));
+        }
+        released
+    }
+}
+
+fn region_drag_value(ui: &mut egui::Ui, marker: Marker, region: &mut AwcRegion, samples: u64) {
+    let range = marker.range(region, samples);
+    let mut position = marker.position(region);
+    let label = ui.label(marker.to_string());
+    let response = ui
+        .add(egui::DragValue::new(&mut position).clamp_range(range))
+        .labelled_by(label.id);
+    // Out of range values are only clamped once they are edited, the error is shown until then
+    if response.changed() {
+        marker.set_position(region, position);
+    }
+}
+
+// Min and max of all channels in each of `columns` equally sized parts of the file
+fn waveform(wav: &Wav, columns: usize) -> Vec<(i16, i16)> {
+    let channels = wav.channels.max(1) as usize;
+    let count = wav.sample_count();
+    (0..columns)
+        .map(|column| {
+            let start = count * column / columns;
+            let end = (count * (column + 1) / columns).max(start + 1).min(count);
+            wav.samples[start * channels..end * channels]
+                .iter()
+                .fold((0, 0), |(min, max), s| (min.min(*s), max.max(*s)))
+        })
+        .collect()
+}
@@ -1,19 +1,27 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
+use audio_forge_core::jobs::JobStatus;
 use eframe::egui;
 use log::error;
-use state::{LoadedTabs, MenuEntry, State};
+use state::{JobOutput, LoadedTabs, MenuEntry, State};
 use strum::IntoEnumIterator;
 
+mod cli;
 mod components;
-mod dat_files;
-mod project_mgmt;
 mod state;
-mod utils;
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
-    ffmpeg_next::init().unwrap();
+
+    // Any argument starts the headless cli instead of the window, it reports its own errors
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+    if let Err(err) = audio_forge_core::init() {
+        error!("Failed to initialize: {:?}", err);
+        std::process::exit(1);
+    }
     // std::env::set_var("WINIT_UNIX_BACKEND", "x11");
 
     let options = eframe::NativeOptions {
@@ -57,6 +65,14 @@ impl AudioForge {
 
                 ui.menu_button("File", |ui| {
                     // if ui.button("Open project").clicked() {} // With the recent opened projects
+                    if ui.button("Rescan sample counts").clicked() {
+                        if let Some(project) = state.active_project.clone() {
+                            state.jobs.spawn("Rescan sample counts", move |job| {
+                                Ok(JobOutput::SampleCounts(project.rescan_samples(job)?))
+                            });
+                        }
+                        ui.close_menu();
+                    }
                     if ui.button("Close project").clicked() {
                         if let Some(project) = state.active_project.as_mut() {
                             let res = project.save();
@@ -83,6 +99,92 @@ impl AudioForge {
         });
     }
 
+    fn job_panel(&mut self, ctx: &egui::Context) {
+        let mut state = self.state.borrow_mut();
+        state.poll_jobs();
+        if state.jobs.is_busy() {
+            // Keep the progress moving while nothing else triggers a repaint
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if state.jobs.jobs().is_empty() {
+            return;
+        }
+
+        let mut cancelled = vec![];
+        let mut clear = false;
+        egui::TopBottomPanel::bottom("job_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Jobs");
+                    if ui.button("Clear finished").clicked() {
+                        clear = true;
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for job in state.jobs.jobs() {
+                            ui.horizontal(|ui| {
+                                ui.label(&job.name);
+                                match &job.status {
+                                    JobStatus::Queued => {
+                                        ui.weak("Queued");
+                                    }
+                                    JobStatus::Running => {
+                                        ui.add(
+                                            egui::ProgressBar::new(job.progress())
+                                                .desired_width(200.0)
+                                                .show_percentage(),
+                                        );
+                                    }
+                                    JobStatus::Finished => {
+                                        ui.label("Done");
+                                    }
+                                    JobStatus::Failed(err) => {
+                                        ui.colored_label(ui.visuals().error_fg_color, err);
+                                    }
+                                    JobStatus::Cancelled => {
+                                        ui.weak("Cancelled");
+                                    }
+                                }
+                                if !job.status.is_done() && ui.button("Cancel").clicked() {
+                                    cancelled.push(job.id);
+                                }
+                            });
+                        }
+                    });
+            });
+
+        for id in cancelled {
+            state.jobs.cancel(id);
+        }
+        if clear {
+            state.jobs.clear_done();
+        }
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Focused text fields handle their own undo
+        if ctx.memory(|m| m.focus().is_some()) {
+            return;
+        }
+        let redo = ctx.input_mut(|i| {
+            i.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            )
+        });
+        let undo = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z));
+
+        let mut state = self.state.borrow_mut();
+        if redo {
+            state.redo();
+        } else if undo {
+            state.undo();
+        }
+    }
+
     fn show_selected_app(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let state = self.state.borrow();
         let mut menu_entry = state.active_menu;
@@ -97,8 +199,10 @@ impl AudioForge {
 
 impl eframe::App for AudioForge {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.handle_shortcuts(ctx);
         // Top tabbed panel
         self.app_tab_bar(ctx);
+        self.job_panel(ctx);
         self.show_selected_app(ctx, frame)
     }
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {
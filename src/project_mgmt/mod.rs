@@ -1,3 +0,0 @@
-pub mod awc;
-pub mod dat54;
-pub mod project;
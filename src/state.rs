@@ -1,9 +1,16 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{
-    components::{awc_generator, occl_generator, project_selector},
-    project_mgmt::project::Project,
+use audio_forge_core::{
+    jobs::{JobId, JobQueue},
+    project_mgmt::{
+        awc::{AwcEntry, AwcPack},
+        history::{Command, History},
+        project::{Project, SampleCountFix},
+    },
 };
+
+use crate::components::{awc_generator, occl_generator, project_selector};
+use log::{error, info};
 use strum::EnumIter;
 
 pub struct LoadedTabs {
@@ -49,6 +56,20 @@ impl MenuEntry {
 pub struct State {
     pub active_menu: MenuEntry,
     pub active_project: Option<Project>,
+    pub history: History,
+    pub jobs: JobQueue<JobOutput>,
+    // Pack and entry names of the running imports, two files with the same name would write the
+    // same wav
+    importing: Vec<(JobId, String, String)>,
+}
+
+/// Result of a background job, applied to the project once the job is done
+pub enum JobOutput {
+    // Packs are referenced by name as their indices can change while the job runs
+    ImportedEntry { pack: String, entry: AwcEntry },
+    ImportedPack(AwcPack),
+    Built(String),
+    SampleCounts(Vec<SampleCountFix>),
 }
 
 impl Default for State {
@@ -56,6 +77,9 @@ impl Default for State {
         Self {
             active_menu: MenuEntry::ProjectSelector,
             active_project: None,
+            history: History::default(),
+            jobs: JobQueue::default(),
+            importing: vec![],
         }
     }
 }
@@ -70,9 +94,96 @@ impl State {
 
     pub fn set_project(&mut self, project: Project) {
         self.active_project = Some(project);
+        self.history.clear();
+        self.jobs.cancel_all();
     }
 
     pub fn close_project(&mut self) {
         self.active_project = None;
+        self.history.clear();
+        self.jobs.cancel_all();
+    }
+
+    /// Applies the results of the background jobs which finished since the last call
+    pub fn poll_jobs(&mut self) {
+        let finished = self.jobs.poll();
+        let jobs = self.jobs.jobs();
+        self.importing
+            .retain(|(id, _, _)| jobs.iter().any(|j| j.id == *id && !j.status.is_done()));
+
+        for (_, output) in finished {
+            let Some(project) = self.active_project.as_ref() else {
+                return;
+            };
+            match output {
+                JobOutput::ImportedEntry { pack, entry } => {
+                    match project.awc_info.iter().position(|p| p.name == pack) {
+                        Some(index)
+                            if project.awc_info[index]
+                                .entries
+                                .iter()
+                                .any(|e| e.name == entry.name) =>
+                        {
+                            error!("{} already has an entry named {}", pack, entry.name)
+                        }
+                        Some(pack) => self.edit(Command::AddEntry { pack, entry }),
+                        None => error!("Pack {} was removed while importing {}", pack, entry.name),
+                    }
+                }
+                JobOutput::ImportedPack(pack) => {
+                    if project.awc_info.iter().any(|p| p.name == pack.name) {
+                        error!("An awc pack named {} already exists", pack.name);
+                    } else {
+                        let command = Command::add_pack(project, pack);
+                        self.edit(command);
+                    }
+                }
+                JobOutput::Built(name) => info!("Built {}", name),
+                JobOutput::SampleCounts(fixes) => {
+                    if let Some(project) = self.active_project.as_mut() {
+                        project.apply_sample_counts(&fixes);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether an entry named `entry` is being imported into `pack`
+    pub fn is_importing(&self, pack: &str, entry: &str) -> bool {
+        self.importing
+            .iter()
+            .any(|(_, p, e)| p == pack && e == entry)
+    }
+
+    /// Reserves the entry name of a running import until its job is done
+    pub fn reserve_import(&mut self, job: JobId, pack: String, entry: String) {
+        self.importing.push((job, pack, entry));
+    }
+
+    /// Applies an undoable edit to the active project
+    pub fn edit(&mut self, command: Command) {
+        if let Some(project) = self.active_project.as_mut() {
+            self.history.apply(project, command);
+        }
+    }
+
+    /// Applies an undoable edit which is merged with the previous edit of the same value,
+    /// see [`History::apply_coalescing`]
+    pub fn edit_coalescing(&mut self, command: Command) {
+        if let Some(project) = self.active_project.as_mut() {
+            self.history.apply_coalescing(project, command);
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(project) = self.active_project.as_mut() {
+            self.history.undo(project);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(project) = self.active_project.as_mut() {
+            self.history.redo(project);
+        }
     }
 }
@@ -1,2 +0,0 @@
-pub mod transcoder;
-pub mod xml;